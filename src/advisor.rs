@@ -0,0 +1,141 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use crate::boards::NormalBoard;
+use crate::chain::simulate;
+use crate::placement::{Pair, Rotation, placements};
+use crate::similar::{added_puyo, similarity};
+
+// only the next and next-next pairs are shown in a game, and the ways to place
+// them grow 22 times over w/ each pair
+pub const MAX_PAIRS: usize = 2;
+
+// a way to place the next pairs, and the db board it gets closest to
+#[derive(Debug)]
+pub struct Advice {
+    // the col and rotation for each pair, in order
+    pub moves: Vec<(usize, Rotation)>,
+    // the board after all the pairs are placed (and any chains go off)
+    pub board: NormalBoard,
+    // the closest db board
+    pub target: NormalBoard,
+    // true if it was the db board's reflection that was the closest
+    pub mirrored: bool,
+    // the num of puyo the target adds, if the board can still be built into it
+    pub added: Option<usize>,
+    pub similarity: f64
+}
+
+impl Advice {
+    // orders advice fr closest to farthest fr its target
+    // boards that can still be built into their target come first, w/ the fewest
+    // puyo left to add, and then the rest go by similarity
+    fn cmp_closeness(&self, other: &Advice) -> Ordering {
+        match (self.added, other.added) {
+            (Some(a), Some(b)) if a != b => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            _ => other.similarity.total_cmp(&self.similarity)
+        }
+    }
+}
+
+// every way to place the pairs in order, w/ the board after each way
+// ways that end up w/ the same board are only listed once
+pub fn place_all(board: &NormalBoard, pairs: &[Pair]) -> Vec<(Vec<(usize, Rotation)>, NormalBoard)> {
+    let mut sequences = vec![(Vec::new(), board.clone())];
+    for pair in pairs {
+        let mut next = Vec::new();
+        let mut seen = HashSet::new();
+        for (moves, board) in &sequences {
+            for p in placements(board, *pair) {
+                // puyo that pop fr a placement are gone before the next pair
+                let placed = simulate(&p.board).final_board;
+                if !seen.insert(placed.padded().to_str()) {
+                    continue;
+                }
+                let mut moves = moves.clone();
+                moves.push((p.col, p.rotation));
+                next.push((moves, placed));
+            }
+        }
+        sequences = next;
+    }
+    sequences
+}
+
+// the n best ways to place the next pairs to get closer to a db board,
+// w/ the closest db board for each
+// only the first MAX_PAIRS pairs are placed
+// if mirror is true, a db board's reflection can be used instead
+pub fn advise(board: &NormalBoard, pairs: &[Pair], db: &[NormalBoard], n: usize, mirror: bool) -> Vec<Advice> {
+    let pairs = &pairs[..pairs.len().min(MAX_PAIRS)];
+    let mut targets: Vec<(NormalBoard, bool)> = db.iter().map(|b| (b.clone(), false)).collect();
+    if mirror {
+        targets.extend(db.iter().filter(|b| !b.is_symmetric()).map(|b| (b.mirror(), true)));
+    }
+
+    let mut advice: Vec<Advice> = Vec::new();
+    for (moves, placed) in place_all(board, pairs) {
+        let mut best: Option<Advice> = None;
+        for (target, mirrored) in &targets {
+            let candidate = Advice {
+                moves: moves.clone(),
+                board: placed.clone(),
+                target: target.clone(),
+                mirrored: *mirrored,
+                added: added_puyo(&placed, target),
+                similarity: similarity(&placed, target)
+            };
+            if best.as_ref().is_none_or(|b| candidate.cmp_closeness(b) == Ordering::Less) {
+                best = Some(candidate);
+            }
+        }
+        advice.extend(best);
+    }
+
+    advice.sort_by(|a, b| a.cmp_closeness(b));
+    advice.truncate(n);
+    advice
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::nb;
+
+    #[test]
+    fn place_all_crosses_pairs() {
+        let board = nb("\nrrgbby");
+        let pairs = [Pair::from_str("rg").unwrap()];
+        assert_eq!(place_all(&board, &pairs).len(), 22);
+
+        // 2 doubles can land in the same spots in either order
+        let pairs = [Pair::from_str("gg").unwrap(), Pair::from_str("gg").unwrap()];
+        let sequences = place_all(&nb("\nrrbbyy"), &pairs);
+        assert!(sequences.len() < 11 * 11);
+        assert!(sequences.iter().all(|(moves, _)| moves.len() == 2));
+    }
+
+    #[test]
+    fn advise_builds_toward_db_boards() {
+        let db = vec![nb("\nyyyyyy"), nb("\n  gr  \nrrgbby")];
+        let board = nb("\nrrgbby");
+        let pairs = [Pair::from_str("gr").unwrap()];
+
+        let advice = advise(&board, &pairs, &db, 3, false);
+        assert_eq!(advice.len(), 3);
+        assert_eq!(advice[0].moves, vec![(2, Rotation::Right)]);
+        assert_eq!(advice[0].added, Some(0));
+        assert_eq!(advice[0].target, db[1]);
+
+        // the reflection of the form is built by flipping the pair
+        let advice = advise(&nb("\nybbgrr"), &pairs, &db, 1, true);
+        assert_eq!(advice[0].moves, vec![(3, Rotation::Left)]);
+        assert!(advice[0].mirrored);
+
+        // only the next and next-next pairs get placed
+        let pairs = [Pair::from_str("gr").unwrap(), Pair::from_str("yy").unwrap(), Pair::from_str("bb").unwrap()];
+        let advice = advise(&board, &pairs, &db, 1, false);
+        assert_eq!(advice[0].moves.len(), MAX_PAIRS);
+    }
+}
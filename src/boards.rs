@@ -1,11 +1,10 @@
-use logru::ast::{self, Rule, Term};
-use logru::textual::TextualUniverse;
 use logru::solver::{query_dfs};
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 // types to rep a normal board, a db board, and a qry board
 // this type has methods to create both db boards and qry boards
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Puyo {
     B,
     G,
@@ -21,39 +20,42 @@ pub struct InvalidPuyoError {
     invalid_puyo: String
 }
 
-// a normal row is a fixed tuple of 6 puyo
+impl fmt::Display for InvalidPuyoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid puyo: {}", self.invalid_puyo)
+    }
+}
+
 #[derive(Debug)]
+pub struct InvalidQryError {
+    invalid_qry: String
+}
+
+impl InvalidQryError {
+    pub fn new(invalid_qry: String) -> Self {
+        InvalidQryError { invalid_qry }
+    }
+}
+
+impl fmt::Display for InvalidQryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid qry: {}", self.invalid_qry)
+    }
+}
+
+// a normal row is a fixed tuple of 6 puyo
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NormalRow {
     puyo: Vec<Puyo>
 }
 
 // a normal board is a variable-length vec of rows
 // only have a fixed length when returning a db or qry board
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NormalBoard {
     rows: Vec<NormalRow>
 }
 
-pub struct DBBoard {
-    // like:
-    //     vec![
-    //         ast::app(row, vec![b.into(), b.into(), y.into(), p.into(), r.into(), r.into()]),
-    //         ast::app(row, vec![r.into(), r.into(), b.into(), y.into(), y.into(), r.into()]),
-    //         ast::app(row, vec![r.into(), b.into(), y.into(), p.into(), r.into(), b.into()]),
-    //     ])
-    padded_board: Vec<ast::Term> // padded to fixed length of 13 rows
-}
-
-pub struct QryBoard {
-    // like:
-//     vec![
-//         ast::app(row, vec![g_var.into(), g_var.into(), b_var.into(), r_var.into(), y_var.into(), y_var.into()]),
-//         rslt_row1.into(),
-//         rslt_row2.into()
-//     ] 
-    padded_board: Vec<ast::Term>
-}
-
 // ***************************************************
 // for now, trying out the text universe, so making types for text db and text qrys
 // ***************************************************
@@ -73,10 +75,54 @@ pub struct TextDBBoard {
     board: String
 }
 
+// symbols at or above this index are dummy vars,
+// which don't have to be diff colors fr each other
+const DUMMY_INDEX_START: usize = 20;
+
+// one side of a constraint in a set-valued qry cell like "[!r]" or "[r|.]"
+#[derive(Debug)]
+pub enum QryOperand {
+    // a symbol in the qry str, like "$0"
+    Sym(String),
+    // an atom in the db, like "l"
+    Atom(String)
+}
+
+// extra checks on a sol'n that can't be written as plain unification
+// each constraint refers to symbols in the qry str (like "$20")
+#[derive(Debug)]
+pub enum QryConstraint {
+    // the symbol has to be one of the 5 colors (not empty or garbage)
+    AnyPuyo(String),
+    // the symbol can't be the same as the operand
+    NotEqual(String, QryOperand),
+    // the symbol has to be the same as one of the operands
+    OneOf(String, Vec<QryOperand>)
+}
+
+// how close a board came to matching a qry when allowing mismatches
+#[derive(Debug)]
+pub struct FuzzyMatch {
+    // the num of mismatched cells, weighted by row if asked for
+    pub score: f64,
+    // the mismatched cells, as (row fr the bottom, col)
+    pub mismatches: Vec<(usize, usize)>
+}
+
+// keeps track of which qry chars have been given which $symbols
+struct SymAlloc {
+    char_to_sym: HashMap<char, String>,
+    // literal colors used in the qry, which the color vars can't also be
+    literal_colors: HashSet<String>,
+    color_index: usize,
+    dummy_index: usize
+}
+
 pub struct TextQryBoard {
     qry_str: String,
-    sym_to_char: HashMap<String, char>, 
-    sym_board: Vec<SymbolRow>
+    sym_board: Vec<SymbolRow>,
+    constraints: Vec<QryConstraint>,
+    literal_colors: HashSet<String>
 }
 
 // ***************************************************
@@ -84,6 +130,14 @@ pub struct TextQryBoard {
 // ***************************************************
 
 impl Puyo {
+    // the 5 colors, for going through color permutations
+    pub const COLORS: [Puyo; 5] = [Puyo::B, Puyo::G, Puyo::P, Puyo::R, Puyo::Y];
+
+    // true for the 5 colors, false for empty and garbage
+    pub fn is_color(self) -> bool {
+        Puyo::COLORS.contains(&self)
+    }
+
     // takes in a str of length 1 and lower-case letters and returns a Puyo
     // puyo: a string slice of length 1, rep'ing a puyo
     pub fn from_str(puyo: &str) -> Result<Self, InvalidPuyoError> {
@@ -99,40 +153,40 @@ impl Puyo {
         }
     }
     
-    pub fn to_str(&self) -> String {
+    pub fn to_str(self) -> String {
         match self {
-            &Puyo::B => String::from("b"),
-            &Puyo::G => String::from("g"),
-            &Puyo::P => String::from("p"),
-            &Puyo::R => String::from("r"),
-            &Puyo::Y => String::from("y"),
-            &Puyo::Empty => String::from(" "),
-            &Puyo::Garbage => String::from("j")
+            Puyo::B => String::from("b"),
+            Puyo::G => String::from("g"),
+            Puyo::P => String::from("p"),
+            Puyo::R => String::from("r"),
+            Puyo::Y => String::from("y"),
+            Puyo::Empty => String::from(" "),
+            Puyo::Garbage => String::from("j")
         }
     }
     
-    pub fn to_color_str(&self) -> String {
+    pub fn to_color_str(self) -> String {
         // println!("\x1b[42;1mHello\x1b[0m");
         match self {
-            &Puyo::B => String::from("\x1b[46;1mb\x1b[0m"),
-            &Puyo::G => String::from("\x1b[42;1mg\x1b[0m"),
-            &Puyo::P => String::from("\x1b[45;1mp\x1b[0m"),
-            &Puyo::R => String::from("\x1b[41;1mr\x1b[0m"),
-            &Puyo::Y => String::from("\x1b[43;1my\x1b[0m"),
-            &Puyo::Empty => String::from(" "),
-            &Puyo::Garbage => String::from("\x1b[47;1mj\x1b[0m")
+            Puyo::B => String::from("\x1b[46;1mb\x1b[0m"),
+            Puyo::G => String::from("\x1b[42;1mg\x1b[0m"),
+            Puyo::P => String::from("\x1b[45;1mp\x1b[0m"),
+            Puyo::R => String::from("\x1b[41;1mr\x1b[0m"),
+            Puyo::Y => String::from("\x1b[43;1my\x1b[0m"),
+            Puyo::Empty => String::from(" "),
+            Puyo::Garbage => String::from("\x1b[47;1mj\x1b[0m")
         } 
     }
     
-    pub fn to_text_db_str(&self) -> String {
+    pub fn to_text_db_str(self) -> String {
         match self {
-            &Puyo::B => String::from("b"),
-            &Puyo::G => String::from("g"),
-            &Puyo::P => String::from("p"),
-            &Puyo::R => String::from("r"),
-            &Puyo::Y => String::from("y"),
-            &Puyo::Empty => String::from("l"),
-            &Puyo::Garbage => String::from("j")
+            Puyo::B => String::from("b"),
+            Puyo::G => String::from("g"),
+            Puyo::P => String::from("p"),
+            Puyo::R => String::from("r"),
+            Puyo::Y => String::from("y"),
+            Puyo::Empty => String::from("l"),
+            Puyo::Garbage => String::from("j")
         }        
     }
 }
@@ -144,13 +198,9 @@ impl NormalRow {
     pub fn from_str(row: &str) -> Result<Self, InvalidPuyoError> {
         let mut row_vec = vec![];
         
-        for puyo in row.chars() {
-            match puyo {
-                p => {
-                    let indiv_puyo = Puyo::from_str(&p.to_string())?;
-                    row_vec.push(indiv_puyo);
-                }
-            }
+        for p in row.chars() {
+            let indiv_puyo = Puyo::from_str(&p.to_string())?;
+            row_vec.push(indiv_puyo);
         }
         
         if row_vec.len() != 6 {
@@ -184,19 +234,22 @@ impl NormalRow {
     // TextDBRow vals are like "row(r, r, g, b, y, y)"
     pub fn to_text_db_row(&self) -> TextDBRow {
         let mut row_str = String::from("row(");
-        let mut i = 0;
         
-        for puyo in &self.puyo {
+        for (i, puyo) in self.puyo.iter().enumerate() {
             row_str.push_str(&puyo.to_text_db_str());
             if i < 5 {
                 row_str.push_str(", ");
             }            
-            i += 1;
         }
         
-        row_str.push_str(")");
+        row_str.push(')');
         TextDBRow{ row: row_str }
     }
+
+    // the row w/ its cols in reverse order
+    pub fn mirror(&self) -> NormalRow {
+        NormalRow { puyo: self.puyo.iter().rev().copied().collect() }
+    }
 }
 
 impl NormalBoard {
@@ -224,7 +277,7 @@ impl NormalBoard {
         
         for row in &self.rows {
             board_str.push_str(&row.to_str());
-            board_str.push_str("\n");
+            board_str.push('\n');
         }
         
         board_str
@@ -235,12 +288,136 @@ impl NormalBoard {
         
         for row in &self.rows {
             board_str.push_str(&row.to_color_str());
-            board_str.push_str("\n");
+            board_str.push('\n');
         }
         
         board_str
     }
     
+    // a copy of the board w/ each puyo changed by f, like for recoloring
+    pub fn map_puyo<F: Fn(Puyo) -> Puyo>(&self, f: F) -> NormalBoard {
+        NormalBoard {
+            rows: self.rows.iter()
+                .map(|row| NormalRow { puyo: row.puyo.iter().map(|p| f(*p)).collect() })
+                .collect()
+        }
+    }
+
+    // a board w/ nothing on it
+    pub fn empty() -> NormalBoard {
+        NormalBoard { rows: Vec::new() }
+    }
+
+    // the board's left-right reflection
+    pub fn mirror(&self) -> NormalBoard {
+        NormalBoard { rows: self.rows.iter().map(|row| row.mirror()).collect() }
+    }
+
+    // true if the board is the same as its reflection
+    pub fn is_symmetric(&self) -> bool {
+        *self == self.mirror()
+    }
+
+    // the puyo at a cell, w/ rows counted fr the bottom (starting at 0)
+    // cells above the top of the board are empty
+    pub fn cell(&self, row: usize, col: usize) -> Puyo {
+        if row >= self.rows.len() || col >= 6 {
+            return Puyo::Empty;
+        }
+        self.rows[self.rows.len() - 1 - row].puyo.get(col).copied().unwrap_or(Puyo::Empty)
+    }
+
+    // sets the puyo at a cell, w/ rows counted fr the bottom (starting at 0)
+    // the board grows empty rows at the top if it needs to
+    pub fn set_cell(&mut self, row: usize, col: usize, puyo: Puyo) {
+        while self.rows.len() <= row {
+            self.rows.insert(0, NormalRow { puyo: vec![Puyo::Empty; 6] });
+        }
+        let index = self.rows.len() - 1 - row;
+        self.rows[index].puyo[col] = puyo;
+    }
+
+    // drops a puyo onto the top of a col
+    // returns false if the col is already full (all 13 rows)
+    pub fn drop_puyo(&mut self, col: usize, puyo: Puyo) -> bool {
+        let height = self.heights()[col];
+        if height >= 13 {
+            return false;
+        }
+        self.set_cell(height, col, puyo);
+        true
+    }
+
+    // drops every puyo down as far as it can go
+    // returns true if anything moved
+    pub fn apply_gravity(&mut self) -> bool {
+        let mut moved = false;
+        for col in 0..6 {
+            let column = self.column(col);
+            for row in 0..self.rows.len() {
+                let puyo = column.get(row).copied().unwrap_or(Puyo::Empty);
+                if self.cell(row, col) != puyo {
+                    self.set_cell(row, col, puyo);
+                    moved = true;
+                }
+            }
+        }
+        moved
+    }
+
+    // removes the empty rows at the top of the board
+    pub fn trim_top(&mut self) {
+        while self.rows.first().is_some_and(|row| row.puyo.iter().all(|p| *p == Puyo::Empty)) {
+            self.rows.remove(0);
+        }
+    }
+
+    // the puyo in a col, fr the bottom up, skipping any empty cells
+    pub fn column(&self, col: usize) -> Vec<Puyo> {
+        (0..self.rows.len()).map(|row| self.cell(row, col)).filter(|p| *p != Puyo::Empty).collect()
+    }
+
+    // the height of each col, counting up to the top puyo in the col
+    pub fn heights(&self) -> [usize; 6] {
+        let mut heights = [0; 6];
+        for (col, height) in heights.iter_mut().enumerate() {
+            *height = (0..self.rows.len()).rev()
+                .find(|row| self.cell(*row, col) != Puyo::Empty)
+                .map_or(0, |row| row + 1);
+        }
+        heights
+    }
+
+    // the board padded out to the full 13 rows w/ empty rows at the top
+    pub fn padded(&self) -> NormalBoard {
+        let mut rows = Vec::with_capacity(13);
+        while rows.len() + self.rows.len() < 13 {
+            rows.push(NormalRow { puyo: vec![Puyo::Empty; 6] });
+        }
+        rows.extend(self.rows.iter().cloned());
+        NormalBoard { rows }
+    }
+
+    // takes in a board fr the text db, like "board(row(l, l, l, l, l, l), ...)."
+    // empty rows at the top are dropped, like in the boards fr run_qry
+    pub fn from_text_db_str(s: &str) -> Result<Self, InvalidPuyoError> {
+        let cleaned: String = s.replace("board(", "")
+            .replace("row(", "")
+            .chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .map(|c| if c == 'l' { ' ' } else { c })
+            .collect();
+        let mut lines: Vec<&str> = Vec::new();
+        let mut start = 0;
+        while start < cleaned.len() {
+            let end = (start + 6).min(cleaned.len());
+            lines.push(&cleaned[start..end]);
+            start = end;
+        }
+        let first_used = lines.iter().position(|line| line.trim() != "").unwrap_or(lines.len());
+        NormalBoard::from_str(&lines[first_used..].join("\n"))
+    }
+
     // creates a TextDBBoard
     pub fn to_text_db_board(&self) -> TextDBBoard {
         let mut board_str = String::new();
@@ -268,90 +445,326 @@ impl NormalBoard {
     }
 }
 
+impl QryOperand {
+    // parses 1 operand inside a "[...]" cell, like "r" or "."
+    fn from_char(c: char, syms: &mut SymAlloc) -> Self {
+        match c {
+            '.' => QryOperand::Atom(String::from("l")),
+            'B' | 'G' | 'P' | 'R' | 'Y' | 'J' => QryOperand::Atom(c.to_ascii_lowercase().to_string()),
+            letter => {
+                // letters declared empty are the empty atom, not a var
+                let sym = syms.letter_sym(letter);
+                if sym.starts_with('$') {
+                    QryOperand::Sym(sym)
+                } else {
+                    QryOperand::Atom(sym)
+                }
+            }
+        }
+    }
+
+    // the symbol for the operand, if it's a symbol and not an atom
+    fn sym(&self) -> Option<&str> {
+        match self {
+            QryOperand::Sym(sym) => Some(sym),
+            QryOperand::Atom(_) => None
+        }
+    }
+
+    // gets the answer for the operand, or None if it's an unbound symbol
+    fn resolve<'a>(&'a self, answers: &'a HashMap<String, String>) -> Option<&'a String> {
+        match self {
+            QryOperand::Sym(sym) => answers.get(sym),
+            QryOperand::Atom(atom) => Some(atom)
+        }
+    }
+}
+
+impl QryConstraint {
+    // the symbol the constraint is on, and any other symbols it refers to
+    pub fn syms(&self) -> (&str, Vec<&str>) {
+        match self {
+            QryConstraint::AnyPuyo(sym) => (sym, Vec::new()),
+            QryConstraint::NotEqual(sym, op) => (sym, op.sym().into_iter().collect()),
+            QryConstraint::OneOf(sym, ops) => (sym, ops.iter().filter_map(|op| op.sym()).collect())
+        }
+    }
+
+    // checks the constraint against a mapping fr symbols to answers (like "$20" -> "g")
+    // symbols that only show up in constraints won't be in the answers,
+    // so they're treated as a color (or anything, for non-color symbols) that
+    // isn't already taken
+    pub fn is_satisfied(&self, answers: &HashMap<String, String>) -> bool {
+        match self {
+            QryConstraint::AnyPuyo(sym) => match answers.get(sym) {
+                Some(ans) => is_puyo_atom(ans),
+                None => false
+            },
+            QryConstraint::NotEqual(sym, op) => match (answers.get(sym), op.resolve(answers)) {
+                (Some(ans), Some(other)) => ans != other,
+                _ => true
+            },
+            QryConstraint::OneOf(sym, ops) => {
+                let ans = match answers.get(sym) {
+                    Some(ans) => ans,
+                    None => return false
+                };
+                ops.iter().any(|op| match op.resolve(answers) {
+                    Some(other) => ans == other,
+                    None => match op {
+                        QryOperand::Sym(op_sym) if is_color_sym(op_sym) => {
+                            is_puyo_atom(ans) && !answers.iter().any(|(k, v)| is_color_sym(k) && v == ans)
+                        }
+                        _ => true
+                    }
+                })
+            }
+        }
+    }
+}
+
+impl SymAlloc {
+    fn new() -> Self {
+        SymAlloc {
+            char_to_sym: HashMap::with_capacity(4),
+            literal_colors: HashSet::new(),
+            color_index: 0,
+            dummy_index: DUMMY_INDEX_START
+        }
+    }
+
+    // if the char already has a symbol, returns that symbol
+    // but if the char doesn't have a symbol yet,
+    // assigns it a symbol and adds it to the mappings
+    // puyo chars get color symbols, everything else gets a dummy symbol
+    fn letter_sym(&mut self, c: char) -> String {
+        if let Some(sym) = self.char_to_sym.get(&c) {
+            return sym.to_string();
+        }
+        let new_symbol = match c {
+            'b' | 'g' | 'p' | 'r' | 'y' => {
+                self.color_index += 1;
+                format!("${}", self.color_index - 1)
+            }
+            _ => self.fresh_dummy()
+        };
+        self.char_to_sym.insert(c, new_symbol.clone());
+        new_symbol
+    }
+
+    // a new dummy var that isn't tied to any char
+    fn fresh_dummy(&mut self) -> String {
+        let new_symbol = format!("${}", self.dummy_index);
+        self.dummy_index += 1;
+        new_symbol
+    }
+
+    // makes the char use the given symbol, along w/ any other chars
+    // that shared the char's old symbol
+    fn alias(&mut self, c: char, sym: &str) {
+        match self.char_to_sym.get(&c).cloned() {
+            Some(old_sym) => {
+                for other_sym in self.char_to_sym.values_mut() {
+                    if *other_sym == old_sym {
+                        *other_sym = sym.to_string();
+                    }
+                }
+            }
+            None => {
+                self.char_to_sym.insert(c, sym.to_string());
+            }
+        }
+    }
+
+    // handles a declaration line like ":diff a b c"
+    //   :same a b c   - the letters are all the same
+    //   :diff a b c   - the letters are all different fr each other
+    //   :empty a b    - the letters are empty
+    //   :filled a b   - the letters are puyo or garbage
+    fn declare(&mut self, decl: &str, constraints: &mut Vec<QryConstraint>) -> Result<(), InvalidQryError> {
+        let mut words = decl.trim_start_matches(':').split_whitespace();
+        let keyword = words.next().unwrap_or("");
+        let mut letters: Vec<char> = Vec::new();
+
+        for word in words {
+            let mut word_chars = word.chars();
+            match (word_chars.next(), word_chars.next()) {
+                (Some(c), None) if c.is_ascii_alphabetic() => letters.push(c),
+                _ => return Err(InvalidQryError { invalid_qry: format!("Invalid declaration: {:?}", decl) })
+            }
+        }
+
+        match keyword {
+            "same" => {
+                // color letters go first so the shared var is still a color var
+                letters.sort_by_key(|c| !matches!(c, 'b' | 'g' | 'p' | 'r' | 'y'));
+                let sym = match letters.iter().find_map(|c| self.char_to_sym.get(c).cloned()) {
+                    Some(sym) => sym,
+                    None => match letters.first() {
+                        Some(c) => self.letter_sym(*c),
+                        None => return Ok(())
+                    }
+                };
+                for c in letters {
+                    self.alias(c, &sym);
+                }
+            }
+            "empty" => {
+                for c in letters {
+                    self.alias(c, "l");
+                }
+            }
+            "filled" => {
+                for c in letters {
+                    let sym = self.letter_sym(c);
+                    constraints.push(QryConstraint::NotEqual(sym, QryOperand::Atom(String::from("l"))));
+                }
+            }
+            "colored" => {
+                for c in letters {
+                    let sym = self.letter_sym(c);
+                    constraints.push(QryConstraint::AnyPuyo(sym));
+                }
+            }
+            "diff" => {
+                for (i, c) in letters.iter().enumerate() {
+                    for other_c in &letters[i+1..] {
+                        // letters declared empty are the empty atom, so the
+                        // constraint goes on whichever letter is still a var
+                        match (QryOperand::from_char(*c, self), QryOperand::from_char(*other_c, self)) {
+                            (QryOperand::Sym(sym), op) | (op, QryOperand::Sym(sym)) => {
+                                constraints.push(QryConstraint::NotEqual(sym, op));
+                            }
+                            (QryOperand::Atom(a), QryOperand::Atom(b)) if a == b => {
+                                return Err(InvalidQryError { invalid_qry: format!("Letters that are always the same can't be different: {:?}", decl) })
+                            }
+                            _ => ()
+                        }
+                    }
+                }
+            }
+            _ => return Err(InvalidQryError { invalid_qry: format!("Unknown declaration: {:?}", decl) })
+        }
+        Ok(())
+    }
+
+    // gets what goes in the qry str for a single cell
+    // and adds any extra constraints the cell needs
+    fn cell_sym(&mut self, cell: &str, constraints: &mut Vec<QryConstraint>) -> Result<String, InvalidQryError> {
+        if let Some(inner) = cell.strip_prefix('[').and_then(|c| c.strip_suffix(']')) {
+            // set-valued cells get their own dummy var,
+            // w/ constraints b/w it and the operands
+            let new_symbol = self.fresh_dummy();
+            let (negated, ops_str) = match inner.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, inner)
+            };
+            let mut ops = Vec::new();
+            for op_str in ops_str.split('|') {
+                let mut op_chars = op_str.chars();
+                match (op_chars.next(), op_chars.next()) {
+                    (Some(c), None) if c == '.' || c.is_ascii_alphabetic() => ops.push(QryOperand::from_char(c, self)),
+                    _ => return Err(InvalidQryError { invalid_qry: format!("Invalid operand {:?} in {:?}", op_str, cell) })
+                }
+            }
+            if negated {
+                for op in ops {
+                    constraints.push(QryConstraint::NotEqual(new_symbol.clone(), op));
+                }
+            } else {
+                // exact colors the cell can be are off limits to the color vars too,
+                // but ones it can't be (after a "!") aren't
+                for op in &ops {
+                    if let QryOperand::Atom(atom) = op {
+                        if is_puyo_atom(atom) {
+                            self.literal_colors.insert(atom.clone());
+                        }
+                    }
+                }
+                constraints.push(QryConstraint::OneOf(new_symbol.clone(), ops));
+            }
+            return Ok(new_symbol);
+        }
+
+        let sym = match cell {
+            // if char is a space, need diff dummy vars for each space
+            " " => self.fresh_dummy(),
+            // a dot means the cell has to be empty,
+            // so use the db's empty atom directly instead of a var
+            "." => String::from("l"),
+            "*" => {
+                // a star is any puyo, so it gets its own dummy var
+                // that's checked to be a color after solving
+                let new_symbol = self.fresh_dummy();
+                constraints.push(QryConstraint::AnyPuyo(new_symbol.clone()));
+                new_symbol
+            }
+            // upper-case letters are literal colors instead of vars
+            "B" | "G" | "P" | "R" | "Y" => {
+                let atom = cell.to_ascii_lowercase();
+                self.literal_colors.insert(atom.clone());
+                atom
+            }
+            "J" => String::from("j"),
+            letter => match letter.chars().next() {
+                Some(c) if c.is_ascii_alphabetic() => self.letter_sym(c),
+                _ => return Err(InvalidQryError { invalid_qry: format!("Invalid cell: {:?}", cell) })
+            }
+        };
+        Ok(sym)
+    }
+}
+
 impl TextQryBoard {
-    pub fn from_str(s: &str) -> Self {
+    // builds the qry str for the logic solver fr a qry like "\n  rr  \n:diff a b"
+    // returns an error for cells or declarations that can't be parsed
+    pub fn from_str(s: &str) -> Result<Self, InvalidQryError> {
         let mut qry_str = String::new();
-        let mut char_to_sym: HashMap<char, String> = HashMap::with_capacity(4);
-        let mut sym_to_char = HashMap::with_capacity(4);
-        // let mut chars_assigned = HashSet::with_capacity(4); // keeps track of what chars have already been assigned a symbol
+        let mut syms = SymAlloc::new();
         let mut sym_board: Vec<SymbolRow> = Vec::new();
-        let mut color_index = 0;
-        let mut dummy_index = 20;
+        let mut constraints = Vec::new();
 
-        let mut char_counter = 0;
         let mut row_counter = 0;
 
+        // lines starting w/ ':' are declarations, not rows
+        let cleaned_board = trim_newlines(s);
+        let (mut decls, lines): (Vec<&str>, Vec<&str>) = cleaned_board.split("\n")
+            .partition(|line| line.starts_with(':'));
+
+        // same/empty change which symbol a letter gets,
+        // so they have to be handled before anything else uses the letters
+        decls.sort_by_key(|decl| !(decl.starts_with(":same") || decl.starts_with(":empty")));
+        for decl in decls {
+            syms.declare(decl, &mut constraints)?;
+        }
+
         // count the num of rows in the string
-        let num_rows = trim_newlines(s).split("\n").collect::<Vec<&str>>().len();
+        let num_rows = lines.len();
+        if num_rows > 13 {
+            return Err(InvalidQryError { invalid_qry: format!("Invalid number of rows: {}", num_rows) });
+        }
 
-        let cleaned_board = trim_newlines(s);
-        for line in cleaned_board.split("\n") {
+        for line in lines {
             // parse a line
-//             println!("-- Line -- ");
             qry_str.push_str("row(");
-            char_counter = 0;  
             let mut sym_row: Vec<String> = Vec::new();
 
-            for puyo_c in line.chars() {              
-//                 println!("symbol: {}", puyo_c);
-                // track which char in the str gets which $symbol
-                match puyo_c {
-                    'b' | 'g' | 'p' | 'r' | 'y' => {
-                        // if the char already has a symbol,
-                        // get the symbol and add it to the qry str
-                        // but if the char doesn't have a symbol yet,
-                        // add assign it a symbol and add it to the syms_to_char mapping
-                        match char_to_sym.get(&puyo_c) {
-                            Some(c) => {
-                                qry_str.push_str(c);
-                                sym_row.push(c.to_string()); // updating the symbol row
-                            }
-                            None => {
-                                let new_symbol = format!("${}", color_index);
-                                char_to_sym.insert(puyo_c, new_symbol.clone());
-                                // also need to update the sym_to_char map
-                                sym_to_char.insert(new_symbol.clone(), puyo_c);
-                                qry_str.push_str(&new_symbol);
-                                sym_row.push(new_symbol); // updating the symbol row
-                                color_index += 1;                        
-                            }
-                        }
-        //                 if char_to_sym.contains_key(&symbol) {
-        //                     qry_str.push_str();
-        //                 }
-                    } // end of case where the char is a puyo char
-                    ' ' => {
-                        // if char is a space, need diff dummy vars for each space
-                        let new_symbol = format!("${}", dummy_index);
-                        qry_str.push_str(&new_symbol);
-                        sym_row.push(new_symbol);
-                        dummy_index += 1;
-                    } // end of case where char is a space        
-                    non_puyo_char => {
-                        match char_to_sym.get(&non_puyo_char) {
-                            Some(c) => {
-                                qry_str.push_str(c);
-                                sym_row.push(c.to_string()); // updating the symbol row
-                            }
-                            None => {
-                                let new_symbol = format!("${}", dummy_index);
-                                char_to_sym.insert(non_puyo_char, new_symbol.clone());
-                                sym_to_char.insert(new_symbol.clone(), non_puyo_char);
-                                qry_str.push_str(&new_symbol);
-                                sym_row.push(new_symbol);
-                                dummy_index += 1;
-                            }
-                        }
-                    } // end of case where non-puyo/non-space char
-                }
+            let cells = split_qry_cells(line)?;
+            if cells.len() != 6 {
+                return Err(InvalidQryError { invalid_qry: format!("Invalid number of cells: {:?}", line) });
+            }
+            for (char_counter, cell) in cells.iter().enumerate() {
+                // track which cell in the str gets which $symbol
+                let sym = syms.cell_sym(cell, &mut constraints)?;
+                qry_str.push_str(&sym);
+                sym_row.push(sym); // updating the symbol row
+
                 // add a comma b/w each symbol
                 if char_counter < 5 {
                     qry_str.push_str(", ");
                 }
-                char_counter += 1;
-
-            } // end of for loop for each char
-            qry_str.push_str(")");
+            } // end of for loop for each cell
+            qry_str.push(')');
             if row_counter < num_rows - 1 { // need to change this to check if this is the last row, not for 13 rows
                 qry_str.push_str(",\n");
             } else {
@@ -373,7 +786,42 @@ impl TextQryBoard {
         // add the start of the qry ("board(")
         qry_str.insert_str(0, "board(");
 
-        TextQryBoard { qry_str: qry_str, sym_to_char: sym_to_char, sym_board: sym_board }
+        Ok(TextQryBoard { qry_str, sym_board, constraints, literal_colors: syms.literal_colors })
+    }
+}
+
+// splits a qry line into its cells
+// most cells are a single char, but "[...]" is a single set-valued cell
+// returns an error if a "[" is never closed
+pub fn split_qry_cells(line: &str) -> Result<Vec<String>, InvalidQryError> {
+    let mut cells = Vec::new();
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '[' {
+            let mut cell = String::from("[");
+            for inner_c in chars.by_ref() {
+                cell.push(inner_c);
+                if inner_c == ']' {
+                    break;
+                }
+            }
+            if !cell.ends_with(']') {
+                return Err(InvalidQryError { invalid_qry: format!("Unclosed \"[\" in {:?}", line) });
+            }
+            cells.push(cell);
+        } else {
+            cells.push(c.to_string());
+        }
+    }
+    Ok(cells)
+}
+
+// color symbols are the ones below the dummy index, like "$0" to "$4"
+fn is_color_sym(sym: &str) -> bool {
+    match sym.strip_prefix('$').and_then(|i| i.parse::<usize>().ok()) {
+        Some(index) => index < DUMMY_INDEX_START,
+        None => false
     }
 }
 
@@ -389,16 +837,212 @@ pub fn is_new_color(colors_assigned: &mut HashSet<String>, color: &str) -> bool
     }    
 }
 
-pub fn run_qry(tu: &mut logru::textual::TextualUniverse, qry: &TextQryBoard) -> Vec<NormalBoard> {
-    let t_qry = tu.prepare_query(&qry.qry_str).unwrap();
+// checks if a term str fr a sol'n is one of the 5 colored puyo
+pub fn is_puyo_atom(term: &str) -> bool {
+    matches!(term, "b" | "g" | "p" | "r" | "y")
+}
+
+impl TextQryBoard {
+    // finds the assignment of the qry's symbols that mismatches the fewest cells
+    // on the board, for when a board only has to be close to the qry
+    // color symbols still have to be diff colors, and a broken constraint
+    // counts as a mismatch on the constraint's cell
+    // mismatches on lower rows count for more if weighted is true
+    // returns None if the board can't match w/in the budget
+    pub fn fuzzy_match(&self, board: &NormalBoard, weighted: bool, budget: f64) -> Option<FuzzyMatch> {
+        // the board values as db atoms, w/ row 0 as the top row
+        let vals: Vec<Vec<String>> = board.padded().rows.iter()
+            .map(|row| row.puyo.iter().map(|p| p.to_text_db_str()).collect())
+            .collect();
+        let weight = |row: usize| if weighted { 1.0 + row as f64 / 12.0 } else { 1.0 };
+
+        // the cells for each var, and the cells w/ atoms that are already mismatched
+        let mut var_cells: HashMap<&str, Vec<(usize, usize)>> = HashMap::new();
+        let mut base_cost = 0.0;
+        let mut mismatches = Vec::new();
+        for (r, sym_row) in self.sym_board.iter().enumerate().take(13) {
+            if let SymbolRow::IndivSymbols(syms) = sym_row {
+                for (c, sym) in syms.iter().enumerate().take(6) {
+                    if sym.starts_with('$') {
+                        var_cells.entry(sym.as_str()).or_default().push((r, c));
+                    } else if *sym != vals[r][c] {
+                        base_cost += weight(r);
+                        mismatches.push((r, c));
+                    }
+                }
+            }
+        }
+
+        // vars that depend on other vars have to be searched together
+        // the rest can each just take their best value
+        let mut coupled: Vec<&str> = Vec::new();
+        for constraint in &self.constraints {
+            let (sym, others) = constraint.syms();
+            if !others.is_empty() {
+                for s in std::iter::once(sym).chain(others) {
+                    if !coupled.contains(&s) {
+                        coupled.push(s);
+                    }
+                }
+            }
+        }
+        for var in var_cells.keys() {
+            if is_color_sym(var) && !coupled.contains(var) {
+                coupled.push(var);
+            }
+        }
+        coupled.sort();
+
+        let var_cost = |var: &str, val: &Option<String>, answers: &HashMap<String, String>| -> f64 {
+            let mut cost = 0.0;
+            for (r, c) in var_cells.get(var).map(|v| v.as_slice()).unwrap_or(&[]) {
+                if val.as_ref() != Some(&vals[*r][*c]) {
+                    cost += weight(*r);
+                }
+            }
+            // an unused val (None) is already mismatched, so its constraints don't add anything
+            if val.is_some() {
+                for constraint in &self.constraints {
+                    if constraint.syms().0 == var && !constraint.is_satisfied(answers) {
+                        cost += var_cells.get(var).and_then(|v| v.first()).map_or(1.0, |(r, _)| weight(*r));
+                    }
+                }
+            }
+            cost
+        };
+        // the vals worth trying for a var: the ones on its cells, or an unused val
+        let candidates = |var: &str| -> Vec<Option<String>> {
+            let mut cands: Vec<Option<String>> = vec![None];
+            for (r, c) in var_cells.get(var).map(|v| v.as_slice()).unwrap_or(&[]) {
+                if !cands.contains(&Some(vals[*r][*c].clone())) {
+                    cands.push(Some(vals[*r][*c].clone()));
+                }
+            }
+            cands
+        };
+
+        // independent vars
+        let mut answers: HashMap<String, String> = HashMap::new();
+        let mut assigned: HashMap<&str, Option<String>> = HashMap::new();
+        let mut indep_vars: Vec<&str> = var_cells.keys().copied().filter(|v| !coupled.contains(v)).collect();
+        indep_vars.sort();
+        for var in indep_vars {
+            let mut best: Option<(f64, Option<String>)> = None;
+            for cand in candidates(var) {
+                let mut cand_answers = HashMap::new();
+                if let Some(val) = &cand {
+                    cand_answers.insert(var.to_string(), val.clone());
+                }
+                let cost = var_cost(var, &cand, &cand_answers);
+                if best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) {
+                    best = Some((cost, cand));
+                }
+            }
+            if let Some((cost, val)) = best {
+                base_cost += cost;
+                if let Some(v) = &val {
+                    answers.insert(var.to_string(), v.clone());
+                }
+                assigned.insert(var, val);
+            }
+        }
+        if base_cost > budget {
+            return None;
+        }
+
+        // coupled vars, searched w/ backtracking
+        let mut best: Option<(f64, Vec<Option<String>>)> = None;
+        let mut stack: Vec<Option<String>> = Vec::new();
+        let cands: Vec<Vec<Option<String>>> = coupled.iter().map(|v| candidates(v)).collect();
+        let mut choice = vec![0usize; coupled.len()];
+        loop {
+            if stack.len() == coupled.len() {
+                // leaf: score the full assignment
+                let mut full_answers = answers.clone();
+                for (var, val) in coupled.iter().zip(&stack) {
+                    if let Some(v) = val {
+                        full_answers.insert(var.to_string(), v.clone());
+                    }
+                }
+                let cost: f64 = base_cost + coupled.iter().zip(&stack)
+                    .map(|(var, val)| var_cost(var, val, &full_answers))
+                    .sum::<f64>();
+                if cost <= budget && best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) {
+                    best = Some((cost, stack.clone()));
+                }
+            } else {
+                let depth = stack.len();
+                if choice[depth] < cands[depth].len() {
+                    let cand = cands[depth][choice[depth]].clone();
+                    choice[depth] += 1;
+                    // color vars have to be diff fr each other and fr the literal colors
+                    let taken = match &cand {
+                        Some(v) if is_color_sym(coupled[depth]) => {
+                            self.literal_colors.contains(v) || coupled.iter().zip(&stack)
+                                .any(|(var, val)| is_color_sym(var) && val.as_ref() == Some(v))
+                        }
+                        _ => false
+                    };
+                    if !taken {
+                        stack.push(cand);
+                        if depth + 1 < coupled.len() {
+                            choice[depth + 1] = 0;
+                        }
+                    }
+                    continue;
+                }
+            }
+            // done w/ this level, go back up
+            if stack.pop().is_none() {
+                break;
+            }
+        }
+
+        let (score, coupled_vals) = best?;
+        let mut full_answers = answers;
+        for (var, val) in coupled.iter().zip(coupled_vals) {
+            if let Some(v) = &val {
+                full_answers.insert(var.to_string(), v.clone());
+            }
+            assigned.insert(var, val);
+        }
+
+        // find the mismatched cells fr the best assignment
+        for (var, val) in &assigned {
+            let cells = var_cells.get(var).map(|v| v.as_slice()).unwrap_or(&[]);
+            for (r, c) in cells {
+                if val.as_ref() != Some(&vals[*r][*c]) {
+                    mismatches.push((*r, *c));
+                }
+            }
+            if val.is_some() && self.constraints.iter().any(|con| con.syms().0 == *var && !con.is_satisfied(&full_answers)) {
+                if let Some(cell) = cells.first() {
+                    mismatches.push(*cell);
+                }
+            }
+        }
+        // switch to rows fr the bottom
+        let mut mismatches: Vec<(usize, usize)> = mismatches.into_iter().map(|(r, c)| (12 - r, c)).collect();
+        mismatches.sort();
+        mismatches.dedup();
+
+        Some(FuzzyMatch { score, mismatches })
+    }
+}
+
+// returns an error if the solver can't parse the qry str
+pub fn run_qry(tu: &mut logru::textual::TextualUniverse, qry: &TextQryBoard) -> Result<Vec<NormalBoard>, InvalidQryError> {
+    let t_qry = match tu.prepare_query(&qry.qry_str) {
+        Ok(q) => q,
+        Err(e) => return Err(InvalidQryError { invalid_qry: format!("{:?} in {:?}", e.kind, qry.qry_str) })
+    };
     let t_solns = query_dfs(tu.inner(), &t_qry);
     let mut answers = HashMap::new();
     let mut nr_solns = Vec::new();
-    const DUMMY_INDEX_START: usize = 20;
     
     'soln: for solution in t_solns {
         let mut soln_rows = Vec::new();
-        let mut colors_assigned = HashSet::new();
+        let mut colors_assigned = qry.literal_colors.clone();
         
         // create mapping fr symbols to puyo/answers
         for (index, var) in solution.into_iter().enumerate() {
@@ -408,7 +1052,7 @@ pub fn run_qry(tu: &mut logru::textual::TextualUniverse, qry: &TextQryBoard) ->
                 // check the sol'n to make sure that all the terms 
                 // correspond to different colors
                 // if have a sol'n that doesn't have unique colors, skip to next sol'n
-                if (index < DUMMY_INDEX_START) && is_new_color(&mut colors_assigned, &tu.pretty().term_to_string(&term)) || (index >= DUMMY_INDEX_START) {
+                if (index >= DUMMY_INDEX_START) || is_new_color(&mut colors_assigned, &tu.pretty().term_to_string(&term)) {
                     answers.insert(symbol, tu.pretty().term_to_string(&term));
                 } else {
                     continue 'soln;
@@ -417,6 +1061,11 @@ pub fn run_qry(tu: &mut logru::textual::TextualUniverse, qry: &TextQryBoard) ->
             } 
 //             println!("inner rslt: {}", rslt_str);
         }
+
+        // skip sol'ns that don't pass the extra checks fr the qry
+        if !qry.constraints.iter().all(|c| c.is_satisfied(&answers)) {
+            continue 'soln;
+        }
         
         // use mapping fr symbols to puyo/answers to create a sol'n NormalBoard
         // fr the symbol board
@@ -465,29 +1114,56 @@ pub fn run_qry(tu: &mut logru::textual::TextualUniverse, qry: &TextQryBoard) ->
         let nb = NormalBoard { rows: soln_rows };
         nr_solns.push(nb);
     } // end of for loop iterating over sol'ns
-    nr_solns
+    Ok(nr_solns)
+}
+
+// gets all the boards loaded into a text universe
+pub fn db_boards(tu: &logru::textual::TextualUniverse) -> Vec<NormalBoard> {
+    let mut boards = Vec::new();
+    for rule in tu.inner().rules() {
+        let rule_str = tu.pretty().rule_to_string(rule);
+        if rule_str.starts_with("board(") {
+            if let Ok(b) = NormalBoard::from_text_db_str(&rule_str) {
+                boards.push(b);
+            }
+        }
+    }
+    boards
 }
 
 // convenience func to load boards to a text universe
-pub fn load_str_board(tu: &mut logru::textual::TextualUniverse, s: &str) {
+// returns the board if it loaded
+pub fn load_str_board(tu: &mut logru::textual::TextualUniverse, s: &str) -> Option<NormalBoard> {
 //     let s_1 = String::from("
 //     bgbyyy
 //     bbgbbb
 //     ggbygp
 //     ");
-    if NormalBoard::from_str(s).is_ok() {
-        let b = NormalBoard::from_str(s).unwrap();
-        tu.load_str(&b.to_text_db_board().board,).unwrap();
-    } else {
-        println!("Error loading board: {}", s);
+    parse_db_board(s).map(|b| load_board(tu, b))
+}
+
+// parses a board str to load, printing why if it can't be parsed
+pub fn parse_db_board(s: &str) -> Option<NormalBoard> {
+    match NormalBoard::from_str(s) {
+        Ok(b) => Some(b),
+        Err(e) => {
+            println!("Error loading board ({}): {}", e, s);
+            None
+        }
     }
 }
 
+// loads an already parsed board to a text universe
+pub fn load_board(tu: &mut logru::textual::TextualUniverse, b: NormalBoard) -> NormalBoard {
+    tu.load_str(&b.to_text_db_board().board,).unwrap();
+    b
+}
+
 /// removes newlines fr the start and end of a str
 /// to be used to create a board
-fn trim_newlines(board: &str) -> &str {
+pub fn trim_newlines(board: &str) -> &str {
     // start
-    let mut cleaned_board = if board.starts_with("\n") {
+    let cleaned_board = if board.starts_with("\n") {
         board.trim_start_matches("\n")
     } else {
         board
@@ -504,12 +1180,219 @@ fn trim_newlines(board: &str) -> &str {
 #[cfg(test)]
 mod board_tests {
     use super::*;
+    use crate::test_utils::load_tu;
 
     #[test]
     fn qry_empty_bottom_row() {
         let q_1 = String::from("\n   rr \n      \n");
-        let qb_1 = TextQryBoard::from_str(&q_1);
+        let qb_1 = TextQryBoard::from_str(&q_1).unwrap();
         let expected_qry_str = "board($1313, $1312, $1311, $1310, $139, $138, $137, $136, $135, $134, $133, row($20, $21, $22, $0, $0, $23),\nrow($24, $25, $26, $27, $28, $29)).";
         assert_eq!(qb_1.qry_str, expected_qry_str);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn qry_dot_is_empty_atom() {
+        let qb = TextQryBoard::from_str("\n.  rr.").unwrap();
+        let expected_qry_str = "board($1313, $1312, $1311, $1310, $139, $138, $137, $136, $135, $134, $133, $132, row(l, $20, $21, $0, $0, l)).";
+        assert_eq!(qb.qry_str, expected_qry_str);
+    }
+
+    #[test]
+    fn qry_dot_only_matches_empty() {
+        let mut tu = load_tu(&["\n   bb \nggrbby", "\n      \nggrbby"]);
+
+        // space is a don't-care, so both boards match
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\n    b \n      ").unwrap()).unwrap();
+        assert_eq!(solns.len(), 2);
+
+        // dot has to be empty, so only the 2nd board matches
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\n    . \n   b  ").unwrap()).unwrap();
+        assert_eq!(solns.len(), 1);
+        assert_eq!(solns[0].to_str(), "ggrbby\n");
+    }
+
+    #[test]
+    fn qry_star_matches_any_puyo() {
+        let mut tu = load_tu(&["\n   jb \nggrbby", "\n    b \nggrbby", "\n   gb \nggrbby"]);
+
+        // star can't be empty or garbage, so only the last board matches
+        let qb = TextQryBoard::from_str("\n   *b \n      ").unwrap();
+        assert_eq!(qb.constraints.len(), 1);
+        let solns = run_qry(&mut tu, &qb).unwrap();
+        assert_eq!(solns.len(), 1);
+        assert_eq!(solns[0].to_str(), "   gb \nggrbby\n");
+    }
+
+    #[test]
+    fn qry_set_cells_are_single_cells() {
+        let qb = TextQryBoard::from_str("\nr[!r] [r|b|.]  ").unwrap();
+        let expected_qry_str = "board($1313, $1312, $1311, $1310, $139, $138, $137, $136, $135, $134, $133, $132, row($0, $20, $21, $22, $23, $24)).";
+        assert_eq!(qb.qry_str, expected_qry_str);
+        assert_eq!(qb.constraints.len(), 2);
+    }
+
+    #[test]
+    fn qry_not_equal_cell() {
+        let mut tu = load_tu(&["\n   bb \nggrbby", "\n   gb \nggrbby", "\n    b \nggrbby"]);
+
+        // anything but b's color, including empty
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\n   [!b]b \n      ").unwrap()).unwrap();
+        assert_eq!(solns.len(), 2);
+
+        // not b's color and not empty
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\n   [!b|.]b \n      ").unwrap()).unwrap();
+        assert_eq!(solns.len(), 1);
+        assert_eq!(solns[0].to_str(), "   gb \nggrbby\n");
+    }
+
+    #[test]
+    fn qry_one_of_cell() {
+        let mut tu = load_tu(&["\n   bb \nggrbby", "\n   gb \nggrbby", "\n    b \nggrbby"]);
+
+        // g is bound by the bottom row
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\n   [g|b]b \ngg    ").unwrap()).unwrap();
+        assert_eq!(solns.len(), 2);
+
+        // r isn't in the qry anywhere else, so it's any color that isn't b's
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\n   [r|.]b \n      ").unwrap()).unwrap();
+        assert_eq!(solns.len(), 2);
+        assert_eq!(solns[0].to_str(), "   gb \nggrbby\n");
+    }
+
+    #[test]
+    fn qry_upper_case_is_literal() {
+        let qb = TextQryBoard::from_str("\nRr[!G]  J").unwrap();
+        let expected_qry_str = "board($1313, $1312, $1311, $1310, $139, $138, $137, $136, $135, $134, $133, $132, row(r, $0, $20, $21, $22, j)).";
+        assert_eq!(qb.qry_str, expected_qry_str);
+    }
+
+    #[test]
+    fn qry_mixed_literal_and_var_colors() {
+        let mut tu = load_tu(&["\nggrbby", "\nbbrggy", "\nrrgbby"]);
+
+        // any pair of the same color
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\nrr    ").unwrap()).unwrap();
+        assert_eq!(solns.len(), 3);
+
+        // has to be a literal b pair
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\nBB    ").unwrap()).unwrap();
+        assert_eq!(solns.len(), 1);
+        assert_eq!(solns[0].to_str(), "bbrggy\n");
+
+        // the r var can't be the literal r
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\nrrR   ").unwrap()).unwrap();
+        assert_eq!(solns.len(), 2);
+
+        // or a literal color the cell can be, but a color after a "!" is only
+        // ruled out for that cell
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\nrr[!B]   ").unwrap()).unwrap();
+        assert_eq!(solns.len(), 3);
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\nrr[!R]   ").unwrap()).unwrap();
+        assert_eq!(solns.len(), 1);
+        assert_eq!(solns[0].to_str(), "rrgbby\n");
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\nrr[G|R]   ").unwrap()).unwrap();
+        assert_eq!(solns.len(), 1);
+        assert_eq!(solns[0].to_str(), "bbrggy\n");
+    }
+
+    #[test]
+    fn qry_declarations() {
+        // same aliases the letters to 1 symbol, and empty uses the empty atom
+        let qb = TextQryBoard::from_str("\naxcd e\n:same a x\n:empty e\n:diff a c\n:filled d").unwrap();
+        let expected_qry_str = "board($1313, $1312, $1311, $1310, $139, $138, $137, $136, $135, $134, $133, $132, row($20, $20, $21, $22, $23, l)).";
+        assert_eq!(qb.qry_str, expected_qry_str);
+        assert_eq!(qb.constraints.len(), 2);
+    }
+
+    #[test]
+    fn qry_rejects_invalid_cells_and_decls() {
+        assert!(TextQryBoard::from_str("\nrr[!b|gg]  ").is_err());
+        assert!(TextQryBoard::from_str("\nrr[]    ").is_err());
+        assert!(TextQryBoard::from_str("\nrr#   ").is_err());
+        // an unclosed bracket can't eat the rest of the row
+        assert!(TextQryBoard::from_str("\nrr[!b   ").is_err());
+        assert!(TextQryBoard::from_str("\nrr    \n:diff a bc").is_err());
+        assert!(TextQryBoard::from_str("\nrr    \n:unknown a").is_err());
+        // rows need exactly 6 cells, and there can't be more than 13 rows
+        assert!(TextQryBoard::from_str("\nrr ").is_err());
+        assert!(TextQryBoard::from_str("\nrrgbbyy").is_err());
+        assert!(TextQryBoard::from_str("\n[r|b]rgbbyy").is_err());
+        assert!(TextQryBoard::from_str(&"\n      ".repeat(14)).is_err());
+        assert!(TextQryBoard::from_str(&"\n      ".repeat(13)).is_ok());
+    }
+
+    #[test]
+    fn qry_declared_letters() {
+        let mut tu = load_tu(&["\nggbb  ", "\nggggy ", "\ngg  y ", "\nbbrryy"]);
+
+        // a and c don't have to be diff by default
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\naacc  ").unwrap()).unwrap();
+        assert_eq!(solns.len(), 4);
+
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\naacc  \n:diff a c\n:filled c").unwrap()).unwrap();
+        assert_eq!(solns.len(), 2);
+
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\naacx e\n:same c x\n:diff a c\n:empty e").unwrap()).unwrap();
+        assert_eq!(solns.len(), 2);
+        assert_eq!(solns[1].to_str(), "gg  y \n");
+
+        // diff works the same whichever side the empty letter is on
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\n  aa  ").unwrap()).unwrap();
+        assert_eq!(solns.len(), 4);
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\n  aa  \n:empty e\n:diff a e").unwrap()).unwrap();
+        assert_eq!(solns.len(), 3);
+        let solns = run_qry(&mut tu, &TextQryBoard::from_str("\n  aa  \n:empty e\n:diff e a").unwrap()).unwrap();
+        assert_eq!(solns.len(), 3);
+        assert!(TextQryBoard::from_str("\n  aa  \n:empty e f\n:diff e f").is_err());
+    }
+
+    #[test]
+    fn board_mirror() {
+        let nb = NormalBoard::from_str("\nr     \nrrbgyj").unwrap();
+        assert_eq!(nb.mirror().to_str(), "     r\njygbrr\n");
+        assert_eq!(nb.mirror().mirror(), nb);
+        assert!(!nb.is_symmetric());
+        assert!(NormalBoard::from_str("\n  rr  \nbgyygb").unwrap().is_symmetric());
+    }
+
+    #[test]
+    fn db_boards_round_trip() {
+        let tu = load_tu(&["\n  r   \n      \nggyyrr", "\nbbbbbb"]);
+        let boards = db_boards(&tu);
+        assert_eq!(boards.len(), 2);
+        assert_eq!(boards[0].to_str(), "  r   \n      \nggyyrr\n");
+        assert_eq!(boards[1].to_str(), "bbbbbb\n");
+        assert_eq!(boards[1].padded().rows.len(), 13);
+    }
+
+    #[test]
+    fn fuzzy_match_counts_mismatches() {
+        let nb = NormalBoard::from_str("\n  r   \nrrgybb").unwrap();
+
+        let exact = TextQryBoard::from_str("\n  r   \nrrgy  ").unwrap().fuzzy_match(&nb, false, 0.0).unwrap();
+        assert_eq!(exact.score, 0.0);
+        assert!(exact.mismatches.is_empty());
+
+        // the r var can only be 1 color, so the top r is off
+        let off = TextQryBoard::from_str("\n  g   \nrrgy. ").unwrap().fuzzy_match(&nb, false, 3.0).unwrap();
+        assert_eq!(off.score, 2.0);
+        assert_eq!(off.mismatches, vec![(0, 4), (1, 2)]);
+        assert!(TextQryBoard::from_str("\n  g   \nrrgy. ").unwrap().fuzzy_match(&nb, false, 1.0).is_none());
+
+        // lower rows count for more when weighted
+        let w = TextQryBoard::from_str("\n  .   \nrrgy. ").unwrap().fuzzy_match(&nb, true, 5.0).unwrap();
+        assert_eq!(w.score, 2.0 + 1.0 + 11.0 / 12.0);
+    }
+
+    #[test]
+    fn fuzzy_match_keeps_colors_distinct() {
+        let nb = NormalBoard::from_str("\nrrrrbb").unwrap();
+        // r and b can't both be red, so 2 cells are off
+        let m = TextQryBoard::from_str("\nrrbbyy").unwrap().fuzzy_match(&nb, false, 6.0).unwrap();
+        assert_eq!(m.score, 2.0);
+
+        // a broken constraint counts as a mismatch on its cell
+        let m = TextQryBoard::from_str("\nrr[!r]   ").unwrap().fuzzy_match(&nb, false, 6.0).unwrap();
+        assert_eq!(m.mismatches, vec![(0, 2)]);
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+use crate::boards::{NormalBoard, Puyo};
+use crate::placement::{DEATH_CELL, Pair, Rotation, reachable_cols};
+use crate::validate::is_realistic;
+
+// 1 pair placed while building a board
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildStep {
+    pub pair: Pair,
+    // the col of the axis puyo
+    pub col: usize,
+    pub rotation: Rotation,
+    // the board after the pair falls
+    pub board: NormalBoard
+}
+
+// finds an order to place pairs in that builds the board fr an empty board,
+// where nothing pops along the way
+// returns None if there isn't one, like when the board has garbage,
+// an odd num of puyo, floating puyo or a group that should have popped
+pub fn build_order(board: &NormalBoard) -> Option<Vec<BuildStep>> {
+    let heights = board.heights();
+    let cols: Vec<Vec<Puyo>> = (0..6).map(|col| board.column(col)).collect();
+    if cols.iter().flatten().any(|p| !p.is_color()) || !cols.iter().flatten().count().is_multiple_of(2) || !is_realistic(board) {
+        return None;
+    }
+
+    // since every board along the way has the same puyo as the finished board
+    // up to some height in each col, the col heights are enough to tell them apart
+    // work backwards, taking off the last pair placed each time
+    let mut dead_ends = HashSet::new();
+    let mut moves = Vec::new();
+    if !unbuild(board, heights, &mut dead_ends, &mut moves) {
+        return None;
+    }
+
+    // replay the pairs forwards
+    let mut built = NormalBoard::empty();
+    let mut steps = Vec::new();
+    for (col, rotation) in moves.into_iter().rev() {
+        let (axis_row, child_col, child_row) = match rotation {
+            Rotation::Up => (built.heights()[col], col, built.heights()[col] + 1),
+            _ => (built.heights()[col], col + 1, built.heights()[col + 1])
+        };
+        let pair = Pair::new(board.cell(axis_row, col), board.cell(child_row, child_col));
+        built.set_cell(axis_row, col, pair.axis);
+        built.set_cell(child_row, child_col, pair.child);
+        steps.push(BuildStep { pair, col, rotation, board: built.clone() });
+    }
+    Some(steps)
+}
+
+// true if the board cut down to the heights can be built, pushing the moves
+// that build it in reverse order
+// the moves are always vertical w/ the axis on the bottom (Up), or
+// horizontal w/ the axis on the left (Right)
+fn unbuild(board: &NormalBoard, heights: [usize; 6], dead_ends: &mut HashSet<[usize; 6]>, moves: &mut Vec<(usize, Rotation)>) -> bool {
+    if heights == [0; 6] {
+        return true;
+    }
+    if dead_ends.contains(&heights) {
+        return false;
+    }
+
+    let mut options = Vec::new();
+    for col in 0..6 {
+        if heights[col] >= 2 {
+            let mut before = heights;
+            before[col] -= 2;
+            options.push((col, Rotation::Up, before));
+        }
+        if col + 1 < 6 && heights[col] >= 1 && heights[col + 1] >= 1 {
+            let mut before = heights;
+            before[col] -= 1;
+            before[col + 1] -= 1;
+            options.push((col, Rotation::Right, before));
+        }
+    }
+
+    for (col, rotation, before) in options {
+        // the pair had to be placeable on the board before it
+        let before_board = cut(board, before);
+        if before[DEATH_CELL.1] > DEATH_CELL.0 {
+            continue;
+        }
+        let reachable = reachable_cols(&before_board);
+        let child_col = if rotation == Rotation::Up { col } else { col + 1 };
+        if !reachable.contains(&col) || !reachable.contains(&child_col) {
+            continue;
+        }
+
+        moves.push((col, rotation));
+        if unbuild(board, before, dead_ends, moves) {
+            return true;
+        }
+        moves.pop();
+    }
+
+    dead_ends.insert(heights);
+    false
+}
+
+// the board w/ everything above the heights taken off
+fn cut(board: &NormalBoard, heights: [usize; 6]) -> NormalBoard {
+    let mut cut = board.clone();
+    for (col, height) in heights.iter().enumerate() {
+        for row in *height..13 {
+            cut.set_cell(row, col, Puyo::Empty);
+        }
+    }
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::nb;
+    use crate::placement::placements;
+
+    #[test]
+    fn build_order_replays() {
+        let board = nb("\n  yp  \nrgyp  \nrrgbpy\nggybbr");
+        let steps = build_order(&board).unwrap();
+        assert_eq!(steps.len(), 9);
+        assert_eq!(steps.last().unwrap().board.heights(), board.heights());
+        assert_eq!(steps.last().unwrap().board.padded(), board.padded());
+
+        // each step has to be a legal placement of its pair
+        let mut current = NormalBoard::empty();
+        for step in &steps {
+            let next = placements(&current, step.pair).into_iter()
+                .find(|p| p.col == step.col && p.rotation == step.rotation)
+                .unwrap();
+            assert_eq!(next.board.padded(), step.board.padded());
+            current = next.board;
+        }
+    }
+
+    #[test]
+    fn unbuildable_boards() {
+        // odd num of puyo
+        assert!(build_order(&nb("\nrgb   ")).is_none());
+        // garbage
+        assert!(build_order(&nb("\nrj    ")).is_none());
+        // a group that should have popped
+        assert!(build_order(&nb("\nrrrr  ")).is_none());
+        // 2 lone puyo that aren't next to each other can't be the same pair
+        assert!(build_order(&nb("\nr g   ")).is_none());
+    }
+}
@@ -0,0 +1,329 @@
+use crate::boards::{NormalBoard, Puyo};
+use crate::groups::{PuyoGroup, neighbors};
+use crate::placement::{Pair, placements};
+use std::cmp::Reverse;
+
+// groups need this many puyo to pop
+pub const POP_SIZE: usize = 4;
+
+// points per garbage puyo sent, under Tsu rules
+pub const TARGET_POINTS: usize = 70;
+
+// the score for 1 step of a chain, under Tsu rules
+#[derive(Debug, PartialEq, Eq)]
+pub struct StepScore {
+    pub chain_power: usize,
+    pub color_bonus: usize,
+    pub group_bonus: usize,
+    // 10 pts for each puyo cleared, times the bonuses (at least 1)
+    pub score: usize
+}
+
+// 1 step of a chain: the groups that popped at the same time
+#[derive(Debug)]
+pub struct ChainStep {
+    pub popped: Vec<PuyoGroup>,
+    // garbage cleared by being next to a popped group, as (row fr the bottom, col)
+    pub garbage_cleared: Vec<(usize, usize)>,
+    // the board after the pops and after everything has fallen
+    pub board: NormalBoard
+}
+
+// everything that happens when a board is left alone
+#[derive(Debug)]
+pub struct ChainResult {
+    pub steps: Vec<ChainStep>,
+    pub final_board: NormalBoard
+}
+
+impl ChainResult {
+    // the num of steps in the chain
+    pub fn length(&self) -> usize {
+        self.steps.len()
+    }
+
+    // true if anything pops at all
+    pub fn fires(&self) -> bool {
+        !self.steps.is_empty()
+    }
+
+    // the score for each step
+    pub fn step_scores(&self) -> Vec<StepScore> {
+        self.steps.iter().enumerate().map(|(i, step)| step.score(i + 1)).collect()
+    }
+
+    // the total score for the whole chain
+    pub fn score(&self) -> usize {
+        self.step_scores().iter().map(|s| s.score).sum()
+    }
+
+    // how many garbage puyo the chain sends
+    pub fn garbage_sent(&self) -> usize {
+        self.score() / TARGET_POINTS
+    }
+}
+
+impl ChainStep {
+    // the score for the step, where chain_num is which step it is (starting at 1)
+    pub fn score(&self, chain_num: usize) -> StepScore {
+        let cleared: usize = self.popped.iter().map(|g| g.size()).sum();
+        let mut colors: Vec<Puyo> = self.popped.iter().map(|g| g.color).collect();
+        colors.sort_by_key(|c| Puyo::COLORS.iter().position(|other| other == c));
+        colors.dedup();
+
+        let chain_power = chain_power(chain_num);
+        let color_bonus = color_bonus(colors.len());
+        let group_bonus: usize = self.popped.iter().map(|g| group_bonus(g.size())).sum();
+        let multiplier = (chain_power + color_bonus + group_bonus).clamp(1, 999);
+
+        StepScore { chain_power, color_bonus, group_bonus, score: 10 * cleared * multiplier }
+    }
+}
+
+// a puyo (or a pair) dropped on the board to start a chain
+#[derive(Debug, PartialEq, Eq)]
+pub struct Trigger {
+    // the puyo dropped, as (col, color), in the order they're dropped
+    pub drops: Vec<(usize, Puyo)>,
+    // the chain it starts
+    pub chain: usize,
+    pub score: usize
+}
+
+impl Trigger {
+    // drops the trigger's puyo on the board
+    // returns false if a col is too full for them
+    pub fn apply(&self, board: &mut NormalBoard) -> bool {
+        self.drops.iter().all(|(col, color)| board.drop_puyo(*col, *color))
+    }
+}
+
+// tries dropping each color on the board into each col, and placing each pair
+// of those colors every way it can go, and returns the drop that starts the
+// longest chain
+// ties go to the drop w/ fewer puyo, and then to the higher score
+// returns None if nothing starts a chain
+pub fn best_trigger(board: &NormalBoard) -> Option<Trigger> {
+    let colors: Vec<Puyo> = Puyo::COLORS.iter().copied()
+        .filter(|c| (0..6).any(|col| board.column(col).contains(c)))
+        .collect();
+
+    // single puyo, and then every pair (the rotations cover both orders)
+    let mut drops: Vec<Vec<(usize, Puyo)>> = Vec::new();
+    for col in 0..6 {
+        for color in &colors {
+            drops.push(vec![(col, *color)]);
+        }
+    }
+    for (i, axis) in colors.iter().enumerate() {
+        for child in &colors[i..] {
+            let pair = Pair::new(*axis, *child);
+            for placement in placements(board, pair) {
+                if let Some(d) = pair.drops(placement.col, placement.rotation) {
+                    drops.push(d.to_vec());
+                }
+            }
+        }
+    }
+
+    let mut best: Option<Trigger> = None;
+    for d in drops {
+        let mut trigger = Trigger { drops: d, chain: 0, score: 0 };
+        let mut dropped = board.clone();
+        if !trigger.apply(&mut dropped) {
+            continue;
+        }
+        let result = simulate(&dropped);
+        trigger.chain = result.length();
+        trigger.score = result.score();
+        let better = match &best {
+            Some(b) => (trigger.chain, Reverse(trigger.drops.len()), trigger.score) > (b.chain, Reverse(b.drops.len()), b.score),
+            None => trigger.chain > 0
+        };
+        if better {
+            best = Some(trigger);
+        }
+    }
+    best
+}
+
+// the longest chain the board can start w/ a trigger, or by itself
+pub fn chain_potential(board: &NormalBoard) -> usize {
+    let own = simulate(board).length();
+    best_trigger(board).map_or(own, |t| t.chain.max(own))
+}
+
+// the Tsu chain power for the nth step of a chain
+pub fn chain_power(chain_num: usize) -> usize {
+    match chain_num {
+        0 | 1 => 0,
+        2 => 8,
+        3 => 16,
+        n => (32 * (n - 3)).min(999)
+    }
+}
+
+// the Tsu bonus for popping groups of n diff colors at once
+pub fn color_bonus(num_colors: usize) -> usize {
+    match num_colors {
+        0 | 1 => 0,
+        2 => 3,
+        3 => 6,
+        4 => 12,
+        _ => 24
+    }
+}
+
+// the Tsu bonus for popping a group of n puyo
+pub fn group_bonus(size: usize) -> usize {
+    match size {
+        0..=4 => 0,
+        5..=10 => size - 3,
+        _ => 10
+    }
+}
+
+// pops any groups of 4+ on the board (and the garbage next to them),
+// then lets everything fall
+// returns None if nothing popped
+pub fn pop_step(board: &NormalBoard) -> Option<ChainStep> {
+    let analysis = board.groups();
+    let popped: Vec<PuyoGroup> = analysis.groups.into_iter().filter(|g| g.size() >= POP_SIZE).collect();
+    if popped.is_empty() {
+        return None;
+    }
+
+    let mut next = board.clone();
+    let mut garbage_cleared = Vec::new();
+    for group in &popped {
+        for (row, col) in &group.cells {
+            next.set_cell(*row, *col, Puyo::Empty);
+            for (nr, nc) in neighbors(*row, *col) {
+                if board.cell(nr, nc) == Puyo::Garbage && !garbage_cleared.contains(&(nr, nc)) {
+                    garbage_cleared.push((nr, nc));
+                }
+            }
+        }
+    }
+    for (row, col) in &garbage_cleared {
+        next.set_cell(*row, *col, Puyo::Empty);
+    }
+    garbage_cleared.sort();
+
+    next.apply_gravity();
+    next.trim_top();
+    Some(ChainStep { popped, garbage_cleared, board: next })
+}
+
+// runs the chain on a board until nothing else pops
+// floating puyo fall first, like they would have before the chain started
+pub fn simulate(board: &NormalBoard) -> ChainResult {
+    let mut current = board.clone();
+    current.apply_gravity();
+    current.trim_top();
+
+    let mut steps = Vec::new();
+    while let Some(step) = pop_step(&current) {
+        current = step.board.clone();
+        steps.push(step);
+    }
+    ChainResult { steps, final_board: current }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::nb;
+
+    #[test]
+    fn no_pops() {
+        let result = simulate(&nb("\nrrgbyy\nrgbbyg"));
+        assert!(!result.fires());
+        assert_eq!(result.length(), 0);
+        assert_eq!(result.final_board.to_str(), "rrgbyy\nrgbbyg\n");
+    }
+
+    #[test]
+    fn two_chain() {
+        // the r's pop, then the g's fall next to the other g's
+        let result = simulate(&nb("\nrgg   \nrrrggb"));
+        assert_eq!(result.length(), 2);
+        assert_eq!(result.steps[0].popped[0].color, Puyo::R);
+        assert_eq!(result.steps[0].board.to_str(), " ggggb\n");
+        assert_eq!(result.steps[1].popped[0].color, Puyo::G);
+        assert_eq!(result.final_board.to_str(), "     b\n");
+    }
+
+    #[test]
+    fn garbage_next_to_pops_clears() {
+        let result = simulate(&nb("\nj     \nrj    \nrrrjj "));
+        assert_eq!(result.length(), 1);
+        assert_eq!(result.steps[0].garbage_cleared, vec![(0, 3), (1, 1), (2, 0)]);
+        assert_eq!(result.final_board.to_str(), "    j \n");
+    }
+
+    #[test]
+    fn floating_puyo_fall_first() {
+        let result = simulate(&nb("\nr     \n      \nrrr   "));
+        assert_eq!(result.length(), 1);
+        assert!(result.final_board.to_str().is_empty());
+    }
+
+    #[test]
+    fn bonus_tables() {
+        let powers: Vec<usize> = (1..=8).map(chain_power).collect();
+        assert_eq!(powers, vec![0, 8, 16, 32, 64, 96, 128, 160]);
+        assert_eq!(chain_power(19), 512);
+        assert_eq!(color_bonus(5), 24);
+        assert_eq!(group_bonus(5), 2);
+        assert_eq!(group_bonus(10), 7);
+        assert_eq!(group_bonus(11), 10);
+    }
+
+    #[test]
+    fn chain_score() {
+        let result = simulate(&nb("\nrgg   \nrrrggb"));
+        let scores = result.step_scores();
+        // a single group of 4 is 40 pts
+        assert_eq!(scores[0].score, 40);
+        // 4 puyo w/ a chain power of 8
+        assert_eq!(scores[1], StepScore { chain_power: 8, color_bonus: 0, group_bonus: 0, score: 320 });
+        assert_eq!(result.score(), 360);
+        assert_eq!(result.garbage_sent(), 5);
+    }
+
+    #[test]
+    fn color_and_group_bonus() {
+        // a group of 5 r and a group of 4 b at once
+        let result = simulate(&nb("\nrrrrrb\nbbbbgg"));
+        assert_eq!(result.length(), 1);
+        assert_eq!(result.step_scores()[0], StepScore { chain_power: 0, color_bonus: 3, group_bonus: 2, score: 450 });
+    }
+
+    #[test]
+    fn finds_best_trigger() {
+        // 1 more g next to the g's (in col 2 or 4) fires the g's and then the r's
+        let board = nb("\n   r  \n   g y\nrrrggy");
+        let trigger = best_trigger(&board).unwrap();
+        assert_eq!(trigger, Trigger { drops: vec![(2, Puyo::G)], chain: 2, score: 360 });
+        assert_eq!(chain_potential(&board), 2);
+    }
+
+    #[test]
+    fn trigger_needs_2_puyo() {
+        let board = nb("\nrr gby");
+        let trigger = best_trigger(&board).unwrap();
+        assert_eq!(trigger.drops, vec![(0, Puyo::R), (0, Puyo::R)]);
+        assert_eq!(chain_potential(&nb("\nrgbyrg")), 0);
+    }
+
+    #[test]
+    fn trigger_can_be_a_mixed_pair() {
+        // a g under an r in col 3 pops the g's, and then the r falls onto the r's
+        // (no single puyo or double starts more than a 1-chain)
+        let board = nb("\n     g\nrrr gg");
+        let trigger = best_trigger(&board).unwrap();
+        assert_eq!(trigger.drops, vec![(3, Puyo::G), (3, Puyo::R)]);
+        assert_eq!(trigger.chain, 2);
+    }
+}
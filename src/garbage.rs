@@ -0,0 +1,118 @@
+use crate::boards::{NormalBoard, Puyo};
+use crate::chain::{Trigger, best_trigger, simulate};
+
+// the most garbage that can fall at once (5 rows)
+pub const MAX_DROP: usize = 30;
+
+// drops n garbage puyo on a board, Tsu style: a full row for every 6 garbage,
+// and then 1 more in each of the extra cols for the rest
+// garbage that doesn't fit in a col disappears
+pub fn drop_garbage(board: &NormalBoard, n: usize, extra_cols: &[usize]) -> NormalBoard {
+    let mut dropped = board.clone();
+    for _ in 0..(n / 6) {
+        for col in 0..6 {
+            dropped.drop_puyo(col, Puyo::Garbage);
+        }
+    }
+    for col in extra_cols {
+        dropped.drop_puyo(*col, Puyo::Garbage);
+    }
+    dropped
+}
+
+// every set of cols the leftover garbage (n % 6) could fall in
+// the game picks them at random, so each set is just as likely
+pub fn extra_col_sets(n: usize) -> Vec<Vec<usize>> {
+    let mut sets = Vec::new();
+    pick_cols(n % 6, 0, &mut Vec::new(), &mut sets);
+    sets
+}
+
+fn pick_cols(k: usize, start: usize, picked: &mut Vec<usize>, sets: &mut Vec<Vec<usize>>) {
+    if picked.len() == k {
+        sets.push(picked.clone());
+        return;
+    }
+    for col in start..6 {
+        picked.push(col);
+        pick_cols(k, col + 1, picked, sets);
+        picked.pop();
+    }
+}
+
+// how a board's main chain holds up after taking garbage
+#[derive(Debug)]
+pub struct Resilience {
+    // the trigger that starts the main chain before any garbage falls
+    pub trigger: Trigger,
+    pub garbage: usize,
+    // the chain the same trigger starts after the garbage falls,
+    // for each set of cols the leftover garbage could fall in
+    pub chains: Vec<usize>
+}
+
+impl Resilience {
+    // the shortest chain left after the garbage falls
+    pub fn worst(&self) -> usize {
+        self.chains.iter().copied().min().unwrap_or(0)
+    }
+
+    // the longest chain left after the garbage falls
+    pub fn best(&self) -> usize {
+        self.chains.iter().copied().max().unwrap_or(0)
+    }
+
+    // true if the main chain still goes off no matter where the garbage falls
+    pub fn always_fires(&self) -> bool {
+        self.worst() > 0
+    }
+}
+
+// drops n garbage (up to MAX_DROP) on a board every way it could fall,
+// and then fires the main chain w/ the same trigger as before
+// returns None if the board has no chain to start
+pub fn resilience(board: &NormalBoard, n: usize) -> Option<Resilience> {
+    let trigger = best_trigger(board)?;
+    let n = n.min(MAX_DROP);
+    let chains = extra_col_sets(n).iter().map(|cols| {
+        let mut dropped = drop_garbage(board, n, cols);
+        trigger.apply(&mut dropped);
+        simulate(&dropped).length()
+    }).collect();
+    Some(Resilience { trigger, garbage: n, chains })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::nb;
+
+    #[test]
+    fn garbage_falls_in_rows() {
+        let board = nb("\nrr    \nrrgbby");
+        assert_eq!(drop_garbage(&board, 8, &[0, 5]).to_str(), "j     \njj   j\nrrjjjj\nrrgbby\n");
+        assert_eq!(extra_col_sets(0), vec![Vec::<usize>::new()]);
+        assert_eq!(extra_col_sets(8).len(), 15);
+        assert_eq!(extra_col_sets(11).len(), 6);
+    }
+
+    #[test]
+    fn garbage_can_bury_triggers() {
+        // an r dropped in col 0 starts a 2-chain
+        let board = nb("\n ggg  \n rrrg ");
+        let before = resilience(&board, 0).unwrap();
+        assert_eq!(before.trigger.drops[0], (0, Puyo::R));
+        assert_eq!(before.chains, vec![2]);
+
+        // a full row of garbage covers the rs, so nothing pops
+        let after = resilience(&board, 6).unwrap();
+        assert_eq!(after.chains, vec![0]);
+        assert!(!after.always_fires());
+
+        // 1 garbage only stops the chain when it falls in col 0
+        let one = resilience(&board, 1).unwrap();
+        assert_eq!(one.chains.len(), 6);
+        assert_eq!(one.worst(), 0);
+        assert_eq!(one.best(), 2);
+    }
+}
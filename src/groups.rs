@@ -0,0 +1,138 @@
+use crate::boards::{NormalBoard, Puyo};
+
+// puyo in the 13th row (row 12 counting fr 0) are hidden and don't connect
+pub const VISIBLE_ROWS: usize = 12;
+
+// a set of connected puyo of the same color
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PuyoGroup {
+    pub color: Puyo,
+    // the cells in the group, as (row fr the bottom, col)
+    pub cells: Vec<(usize, usize)>
+}
+
+// all the groups on a board, and which groups touch each other
+#[derive(Debug)]
+pub struct GroupAnalysis {
+    pub groups: Vec<PuyoGroup>,
+    // touching[i] has the indices of the groups next to group i
+    pub touching: Vec<Vec<usize>>
+}
+
+impl PuyoGroup {
+    pub fn size(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+impl GroupAnalysis {
+    // the groups w/ at least n puyo, like n = 4 for the ones that pop
+    pub fn groups_of_at_least(&self, n: usize) -> Vec<&PuyoGroup> {
+        self.groups.iter().filter(|g| g.size() >= n).collect()
+    }
+}
+
+// the cells next to a cell (up, down, left, right) that are on the visible board
+pub fn neighbors(row: usize, col: usize) -> Vec<(usize, usize)> {
+    let mut cells = Vec::with_capacity(4);
+    if row > 0 {
+        cells.push((row - 1, col));
+    }
+    if row + 1 < VISIBLE_ROWS {
+        cells.push((row + 1, col));
+    }
+    if col > 0 {
+        cells.push((row, col - 1));
+    }
+    if col + 1 < 6 {
+        cells.push((row, col + 1));
+    }
+    cells
+}
+
+impl NormalBoard {
+    // finds the groups of connected same-colored puyo on the board
+    // garbage and empty cells aren't in any group
+    pub fn groups(&self) -> GroupAnalysis {
+        let mut groups: Vec<PuyoGroup> = Vec::new();
+        let mut group_of = [[None; 6]; VISIBLE_ROWS];
+
+        for row in 0..VISIBLE_ROWS {
+            for col in 0..6 {
+                let color = self.cell(row, col);
+                if !color.is_color() || group_of[row][col].is_some() {
+                    continue;
+                }
+
+                // flood fill fr this cell
+                let index = groups.len();
+                let mut cells = Vec::new();
+                let mut stack = vec![(row, col)];
+                group_of[row][col] = Some(index);
+                while let Some((r, c)) = stack.pop() {
+                    cells.push((r, c));
+                    for (nr, nc) in neighbors(r, c) {
+                        if group_of[nr][nc].is_none() && self.cell(nr, nc) == color {
+                            group_of[nr][nc] = Some(index);
+                            stack.push((nr, nc));
+                        }
+                    }
+                }
+                cells.sort();
+                groups.push(PuyoGroup { color, cells });
+            }
+        }
+
+        let mut touching = vec![Vec::new(); groups.len()];
+        for (index, group) in groups.iter().enumerate() {
+            for (r, c) in &group.cells {
+                for (nr, nc) in neighbors(*r, *c) {
+                    if let Some(other) = group_of[nr][nc] {
+                        if other != index && !touching[index].contains(&other) {
+                            touching[index].push(other);
+                        }
+                    }
+                }
+            }
+            touching[index].sort();
+        }
+
+        GroupAnalysis { groups, touching }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_groups_and_sizes() {
+        let nb = NormalBoard::from_str("\n  r   \nrrgbbj\nrgggbb").unwrap();
+        let analysis = nb.groups();
+        let sizes: Vec<(Puyo, usize)> = analysis.groups.iter().map(|g| (g.color, g.size())).collect();
+        assert_eq!(sizes, vec![(Puyo::R, 3), (Puyo::G, 4), (Puyo::B, 4), (Puyo::R, 1)]);
+        assert_eq!(analysis.groups[1].cells, vec![(0, 1), (0, 2), (0, 3), (1, 2)]);
+        assert_eq!(analysis.groups_of_at_least(4).len(), 2);
+        // garbage isn't in any group
+        assert!(analysis.groups.iter().all(|g| !g.cells.contains(&(1, 5))));
+        assert_eq!(analysis.groups[3].cells, vec![(2, 2)]);
+    }
+
+    #[test]
+    fn finds_touching_groups() {
+        let nb = NormalBoard::from_str("\n  r   \nrrgbbj\nrgggbb").unwrap();
+        let analysis = nb.groups();
+        assert_eq!(analysis.touching[0], vec![1]);
+        assert_eq!(analysis.touching[1], vec![0, 2, 3]);
+        assert_eq!(analysis.touching[3], vec![1]);
+    }
+
+    #[test]
+    fn hidden_row_doesnt_connect() {
+        let rows = vec!["r     "; 13];
+        let nb = NormalBoard::from_str(&rows.join("\n")).unwrap();
+        let analysis = nb.groups();
+        assert_eq!(analysis.groups.len(), 1);
+        assert_eq!(analysis.groups[0].size(), 12);
+    }
+}
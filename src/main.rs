@@ -1,36 +1,355 @@
 mod parse;
+mod advisor;
 mod boards;
+mod build;
+mod chain;
+mod garbage;
+mod groups;
+mod opening;
+mod placement;
+mod progression;
+mod search;
+mod similar;
+mod validate;
+#[cfg(test)]
+mod test_utils;
 
 use crate::parse::parse_sheet;
-use crate::boards::{TextQryBoard, run_qry};
+use crate::advisor::{MAX_PAIRS, advise};
+use crate::boards::{NormalBoard, db_boards};
+use crate::build::build_order;
+use crate::chain::{best_trigger, simulate};
+use crate::garbage::resilience;
+use crate::opening::lookup;
+use crate::placement::{Pair, placements, top_out};
+use crate::progression::{Catalog, CatalogEntry};
+use crate::search::{run_search, superset_search};
+use crate::similar::{Ranking, nearest};
+use crate::validate::validate;
 use calamine::{Xlsx, open_workbook};
 use logru::textual::TextualUniverse;
 
+// cmds:
+//   sim_boards                      - runs the qry in the "query" sheet
+//   sim_boards nearest [n] [mirror] [edit]
+//                                   - lists the n db boards most similar
+//                                     to the board in the "query" sheet,
+//                                     or w/ the lowest edit distance for "edit"
+//   sim_boards superset             - lists the db boards that can still be
+//                                     built fr the board in the "query" sheet
+//   sim_boards sim                  - shows each step of the chain on the
+//                                     board in the "query" sheet
+//   sim_boards groups               - lists the groups on the board in the
+//                                     "query" sheet and which ones touch
+//   sim_boards chains               - lists the chain length of each db board
+//   sim_boards opening <pair> [pair...]
+//                                   - lists the db boards tagged as openings for
+//                                     the first pairs of a game
+//   sim_boards sequences            - lists the stages of each sequence of db boards
+//   sim_boards validate             - lists the warnings for each db board
+//   sim_boards garbage [n]          - lists how the chain on each db board
+//                                     holds up after n garbage fall on it
+//   sim_boards build [db]           - shows an order of pairs that builds the
+//                                     board in the "query" sheet, or says if
+//                                     each db board can be built for "db"
+//   sim_boards place <pair>         - lists every way to place a pair like "rg"
+//                                     on the board in the "query" sheet
+//   sim_boards next <pair> [pair] [n] [mirror]
+//                                   - lists the n best ways to place the next
+//                                     pairs on the board in the "query" sheet
+//                                     to get closer to a db board
+//   any cmd w/ --strict             - skips db boards that can't happen in a real game
 fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    // "--strict" anywhere skips db boards that can't happen in a real game
+    let strict = args.iter().any(|a| a == "--strict");
+    args.retain(|a| a != "--strict");
+
     // load db
     let book = "base_db.xlsx";
     let mut excel: Xlsx<_> = open_workbook(book).unwrap();
     let mut tu = TextualUniverse::new();
-    parse_sheet(&mut excel, "key", &mut tu, false);
-    parse_sheet(&mut excel, "l-shape", &mut tu, false);
-    parse_sheet(&mut excel, "flat", &mut tu, false);
+    let mut catalog = Catalog::default();
+    parse_sheet(&mut excel, "key", &mut tu, false, strict, &mut catalog);
+    parse_sheet(&mut excel, "l-shape", &mut tu, false, strict, &mut catalog);
+    parse_sheet(&mut excel, "flat", &mut tu, false, strict, &mut catalog);
 
     // load qry
-    let qry = parse_sheet(&mut excel, "query", &mut tu, true);
-    match qry {
-        Some(s) => {
-            // run qry
-            let qry_board = TextQryBoard::from_str(&s);
-            let nb_solns = run_qry(&mut tu, &qry_board);
+    let qry = parse_sheet(&mut excel, "query", &mut tu, true, false, &mut catalog);
+    match (qry, args.first().map(|a| a.as_str())) {
+        (None, _) => println!("Invalid Query!"),
+        (Some(s), None) | (Some(s), Some("search")) => print_search(&mut tu, &catalog, &s),
+        (Some(s), Some("nearest")) => {
+            let n = args.get(1).and_then(|a| a.parse().ok()).unwrap_or(10);
+            let mirror = args.iter().any(|a| a == "mirror");
+            let ranking = if args.iter().any(|a| a == "edit") { Ranking::EditDistance } else { Ranking::Similarity };
+            print_nearest(&tu, &catalog, &s, n, mirror, ranking);
+        }
+        (Some(s), Some("superset")) => print_superset(&mut tu, &s),
+        (Some(s), Some("sim")) => print_sim(&s),
+        (Some(s), Some("groups")) => print_groups(&s),
+        (Some(_), Some("chains")) => print_chains(&tu, &catalog),
+        (Some(_), Some("sequences")) => print_sequences(&catalog),
+        (Some(_), Some("opening")) => {
+            let pairs: Vec<Pair> = args[1..].iter().filter_map(|a| Pair::from_str(a)).collect();
+            if pairs.is_empty() {
+                println!("No pairs given");
+            } else {
+                print_openings(&catalog, &pairs);
+            }
+        }
+        (Some(_), Some("build")) if args.get(1).is_some_and(|a| a == "db") => print_buildable(&tu),
+        (Some(s), Some("build")) => print_build(&s),
+        (Some(_), Some("validate")) => print_warnings(&tu),
+        (Some(_), Some("garbage")) => {
+            let n = args.get(1).and_then(|a| a.parse().ok()).unwrap_or(6);
+            print_resilience(&tu, n);
+        }
+        (Some(s), Some("place")) => match args.get(1).and_then(|a| Pair::from_str(a)) {
+            Some(pair) => print_placements(&s, pair),
+            None => println!("Invalid pair: {}", args.get(1).map(|a| a.as_str()).unwrap_or(""))
+        },
+        (Some(s), Some("next")) => {
+            let pairs: Vec<Pair> = args[1..].iter().filter_map(|a| Pair::from_str(a)).collect();
+            let n = args[1..].iter().find_map(|a| a.parse().ok()).unwrap_or(10);
+            let mirror = args.iter().any(|a| a == "mirror");
+            if pairs.is_empty() {
+                println!("No pairs given");
+            } else if pairs.len() > MAX_PAIRS {
+                println!("Only the next {} pairs can be given", MAX_PAIRS);
+            } else {
+                print_advice(&tu, &s, &pairs, n, mirror);
+            }
+        }
+        (Some(_), Some(cmd)) => println!("Unknown cmd: {}", cmd)
+    }
+    
+}
 
-            let mut index = 0;
+fn print_search(tu: &mut TextualUniverse, catalog: &Catalog, s: &str) {
+    // run qry
+    let matches = match run_search(tu, s) {
+        Ok(m) => m,
+        Err(e) => {
+            println!("Invalid qry ({}): {}", e, s);
+            return;
+        }
+    };
+    // each sequence is only shown in full the 1st time 1 of its stages matches
+    let mut shown_sequences: Vec<String> = Vec::new();
 
-            for soln in &nb_solns {
-                println!("Solution {}: \n{}", index, soln.to_color_str());
-                index += 1;
-            }            
+    for (index, m) in matches.iter().enumerate() {
+        let mirrored = if m.mirrored { ", mirrored" } else { "" };
+        println!("Solution {} ({}, col {}, row {}{}, heights {:?}, {}, {}): \n{}", index, describe(catalog, &m.board), m.col_offset, m.row_offset, mirrored, m.board.heights(), chain_summary(&m.board), trigger_summary(&m.board), m.board.to_color_str());
+        if !m.mismatches.is_empty() {
+            println!("{} mismatches (score {}): {:?}\n", m.mismatches.len(), m.score, m.mismatches);
         }
-        None => println!("Invalid Query!")
+
+        let stages = catalog.sequence_of(&m.board);
+        if let Some((name, _)) = stages.first().and_then(|e| e.meta.sequence.clone()) {
+            if !shown_sequences.contains(&name) {
+                print_sequence(&name, &stages);
+                shown_sequences.push(name);
+            }
+        }
+    }            
+}
+
+fn print_openings(catalog: &Catalog, pairs: &[Pair]) {
+    let openings = lookup(catalog, pairs);
+    if openings.is_empty() {
+        println!("No boards are tagged as openings for these pairs");
     }
-    
-}
\ No newline at end of file
+    for (index, o) in openings.iter().enumerate() {
+        println!("Opening {} ({}, for {}): \n{}", index, o.entry.meta.describe(), o.pattern, o.board.to_color_str());
+    }
+}
+
+fn print_sequences(catalog: &Catalog) {
+    for name in catalog.sequence_names() {
+        print_sequence(name, &catalog.sequence(name));
+    }
+}
+
+// shows every stage of a sequence
+fn print_sequence(name: &str, stages: &[&CatalogEntry]) {
+    println!("Sequence {}:", name);
+    for entry in stages {
+        println!("  {}: \n{}", entry.meta.describe(), entry.board.to_color_str());
+    }
+}
+
+// the label and other metadata of a db board, like "k-1 in Key (key)"
+fn describe(catalog: &Catalog, board: &NormalBoard) -> String {
+    catalog.meta_of(board).map_or_else(|| String::from("unlabelled"), |m| m.describe())
+}
+
+// parses the board in the "query" sheet for the cmds that take a board
+fn qry_board(s: &str) -> Option<NormalBoard> {
+    match NormalBoard::from_str(s) {
+        Ok(b) => Some(b),
+        Err(e) => {
+            println!("Invalid board ({}): {}", e, s);
+            None
+        }
+    }
+}
+
+fn print_nearest(tu: &TextualUniverse, catalog: &Catalog, s: &str, n: usize, mirror: bool, ranking: Ranking) {
+    let board = match qry_board(s) {
+        Some(b) => b,
+        None => return
+    };
+
+    for (index, sb) in nearest(&board, &db_boards(tu), n, mirror, ranking).iter().enumerate() {
+        let mirrored = if sb.mirrored { ", mirrored" } else { "" };
+        let score = match ranking {
+            Ranking::Similarity => format!("similarity {:.3}", sb.score),
+            Ranking::EditDistance => format!("edit distance {}", sb.score)
+        };
+        println!("Board {} ({}, {}{}): \n{}", index, describe(catalog, &sb.board), score, mirrored, sb.board.to_color_str());
+    }
+}
+
+fn print_superset(tu: &mut TextualUniverse, s: &str) {
+    let board = match qry_board(s) {
+        Some(b) => b,
+        None => return
+    };
+
+    for (index, sm) in superset_search(tu, &board).iter().enumerate() {
+        let added: Vec<String> = sm.added.iter().map(|(p, n)| format!("{} {}", n, p.to_str())).collect();
+        println!("Board {} (add {}): \n{}", index, added.join(", "), sm.board.to_color_str());
+    }
+}
+
+fn print_sim(s: &str) {
+    let board = match qry_board(s) {
+        Some(b) => b,
+        None => return
+    };
+
+    let result = simulate(&board);
+    if !result.fires() {
+        println!("Nothing pops");
+    }
+    for ((index, step), score) in result.steps.iter().enumerate().zip(result.step_scores()) {
+        let sizes: Vec<String> = step.popped.iter().map(|g| format!("{} {}", g.size(), g.color.to_str())).collect();
+        println!("Step {} (popped {}, {} garbage cleared, score {}): \n{}", index + 1, sizes.join(", "), step.garbage_cleared.len(), score.score, step.board.to_color_str());
+    }
+    println!("{}", chain_summary(&board));
+}
+
+fn print_groups(s: &str) {
+    let board = match qry_board(s) {
+        Some(b) => b,
+        None => return
+    };
+
+    let analysis = board.groups();
+    for (index, group) in analysis.groups.iter().enumerate() {
+        println!("Group {} ({} {}, touching {:?}): {:?}", index, group.size(), group.color.to_str(), analysis.touching[index], group.cells);
+    }
+}
+
+fn print_chains(tu: &TextualUniverse, catalog: &Catalog) {
+    for (index, board) in db_boards(tu).iter().enumerate() {
+        println!("Board {} ({}, {}, {}): \n{}", index, describe(catalog, board), chain_summary(board), trigger_summary(board), board.to_color_str());
+    }
+}
+
+fn print_build(s: &str) {
+    let board = match qry_board(s) {
+        Some(b) => b,
+        None => return
+    };
+
+    match build_order(&board) {
+        Some(steps) => {
+            for (index, step) in steps.iter().enumerate() {
+                println!("Step {} ({}{} in col {}, {:?}): \n{}", index + 1, step.pair.axis.to_str(), step.pair.child.to_str(), step.col, step.rotation, step.board.to_color_str());
+            }
+        }
+        None => println!("No order of pairs builds this board")
+    }
+}
+
+fn print_buildable(tu: &TextualUniverse) {
+    for (index, board) in db_boards(tu).iter().enumerate() {
+        let summary = match build_order(board) {
+            Some(steps) => format!("buildable in {} pairs", steps.len()),
+            None => String::from("not buildable")
+        };
+        println!("Board {} ({}): \n{}", index, summary, board.to_color_str());
+    }
+}
+
+fn print_warnings(tu: &TextualUniverse) {
+    for (index, board) in db_boards(tu).iter().enumerate() {
+        let warnings = validate(board);
+        if warnings.is_empty() {
+            continue;
+        }
+        println!("Board {} (room {:?}): \n{}", index, top_out(board).room, board.to_color_str());
+        for warning in warnings {
+            println!("  {}", warning);
+        }
+        println!();
+    }
+}
+
+fn print_resilience(tu: &TextualUniverse, n: usize) {
+    for (index, board) in db_boards(tu).iter().enumerate() {
+        let summary = match resilience(board, n) {
+            Some(r) if r.always_fires() => format!("{}-chain becomes {} to {} after {} garbage", r.trigger.chain, r.worst(), r.best(), r.garbage),
+            Some(r) => format!("{}-chain becomes {} to {} after {} garbage, and can fail to fire", r.trigger.chain, r.worst(), r.best(), r.garbage),
+            None => String::from("no chain")
+        };
+        println!("Board {} ({}): \n{}", index, summary, board.to_color_str());
+    }
+}
+
+fn print_placements(s: &str, pair: Pair) {
+    let board = match qry_board(s) {
+        Some(b) => b,
+        None => return
+    };
+
+    for (index, p) in placements(&board, pair).iter().enumerate() {
+        println!("Placement {} (col {}, {:?}): \n{}", index, p.col, p.rotation, p.board.to_color_str());
+    }
+}
+
+fn print_advice(tu: &TextualUniverse, s: &str, pairs: &[Pair], n: usize, mirror: bool) {
+    let board = match qry_board(s) {
+        Some(b) => b,
+        None => return
+    };
+
+    for (index, a) in advise(&board, pairs, &db_boards(tu), n, mirror).iter().enumerate() {
+        let moves: Vec<String> = a.moves.iter().map(|(col, rotation)| format!("col {} {:?}", col, rotation)).collect();
+        let closeness = match a.added {
+            Some(added) => format!("add {} more", added),
+            None => format!("similarity {:.3}", a.similarity)
+        };
+        let mirrored = if a.mirrored { ", mirrored" } else { "" };
+        println!("Advice {} ({}; {}{}): \n{}\ntoward: \n{}", index, moves.join(", then "), closeness, mirrored, a.board.to_color_str(), a.target.to_color_str());
+    }
+}
+
+// the chain length, score and garbage sent for a board, like "5-chain, score 4840, 69 garbage"
+fn chain_summary(board: &NormalBoard) -> String {
+    let result = simulate(board);
+    format!("{}-chain, score {}, {} garbage", result.length(), result.score(), result.garbage_sent())
+}
+
+// the best trigger for a board, like "potential 7 (r in col 3, g in col 4)"
+fn trigger_summary(board: &NormalBoard) -> String {
+    match best_trigger(board) {
+        Some(t) => {
+            let drops: Vec<String> = t.drops.iter().map(|(col, color)| format!("{} in col {}", color.to_str(), col)).collect();
+            format!("potential {} ({})", t.chain, drops.join(", "))
+        }
+        None => String::from("potential 0")
+    }
+}
@@ -0,0 +1,166 @@
+use crate::boards::{NormalBoard, Puyo};
+use crate::placement::Pair;
+use crate::progression::{Catalog, CatalogEntry};
+use crate::similar::{color_perms, recolor};
+
+// the first pairs an opening assumes, fr a tag like "rr rg" or "AA AB"
+// tags in colors (b g p r y) are the colors the board was built w/,
+// and tags in letters (A to E) just give the pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tsumo {
+    pub pairs: Vec<Pair>,
+    // true if the tag was in colors, so the board can be recolored to match
+    pub literal: bool
+}
+
+// a db board for the first pairs of a game
+#[derive(Debug)]
+pub struct Opening<'a> {
+    pub entry: &'a CatalogEntry,
+    // the board, recolored to the pairs' colors if its tag was in colors
+    pub board: NormalBoard,
+    // the tag's pattern, like "AA AB"
+    pub pattern: String
+}
+
+impl Tsumo {
+    // takes in a tag like "rr rg", "AA AB" or "AAAB"
+    pub fn from_str(tag: &str) -> Option<Self> {
+        let chars: Vec<char> = tag.chars().filter(|c| !c.is_whitespace()).collect();
+        if chars.is_empty() || !chars.len().is_multiple_of(2) {
+            return None;
+        }
+        let literal = chars.iter().all(|c| c.is_ascii_lowercase());
+        let mut puyo = Vec::with_capacity(chars.len());
+        for c in chars {
+            let p = if literal {
+                Puyo::from_str(&c.to_string()).ok().filter(|p| p.is_color())?
+            } else {
+                // letters stand in for colors, A for the 1st color and so on
+                *Puyo::COLORS.get((c as usize).checked_sub('A' as usize)?)?
+            };
+            puyo.push(p);
+        }
+        let pairs = puyo.chunks(2).map(|p| Pair::new(p[0], p[1])).collect();
+        Some(Tsumo { pairs, literal })
+    }
+
+    // the pattern of the pairs, like "AA AB", w/ the letters in each pair sorted
+    // out of every way of turning the colors into letters, it's the one that
+    // comes first alphabetically, so it's the same no matter which colors the
+    // pairs are, or which way round each pair is
+    pub fn pattern(&self) -> String {
+        color_perms().iter().map(|perm| {
+            let letters: Vec<String> = self.pairs.iter().map(|pair| {
+                let mut letters = [letter(recolor(pair.axis, perm)), letter(recolor(pair.child, perm))];
+                letters.sort();
+                letters.iter().collect()
+            }).collect();
+            letters.join(" ")
+        }).min().unwrap_or_default()
+    }
+
+    // the recoloring that turns the tag's pairs into the given pairs,
+    // if the given pairs start w/ the same pattern
+    // recolorings that keep each pair the same way round are tried first
+    pub fn match_perm(&self, pairs: &[Pair]) -> Option<[Puyo; 5]> {
+        if pairs.len() < self.pairs.len() {
+            return None;
+        }
+        let perms = color_perms();
+        let matches = |perm: &[Puyo; 5], either_way: bool| {
+            self.pairs.iter().zip(pairs).all(|(tag, pair)| {
+                let (a, c) = (recolor(tag.axis, perm), recolor(tag.child, perm));
+                (a, c) == (pair.axis, pair.child) || (either_way && (c, a) == (pair.axis, pair.child))
+            })
+        };
+        perms.iter().find(|perm| matches(perm, false))
+            .or_else(|| perms.iter().find(|perm| matches(perm, true)))
+            .copied()
+    }
+}
+
+// the letter for a color, by where it is in Puyo::COLORS
+fn letter(puyo: Puyo) -> char {
+    let index = Puyo::COLORS.iter().position(|c| *c == puyo).unwrap_or(0);
+    (b'A' + index as u8) as char
+}
+
+// the db boards tagged w/ a tsumo pattern that the pairs start w/
+// boards w/ longer tags (that match more of the pairs) come first
+pub fn lookup<'a>(catalog: &'a Catalog, pairs: &[Pair]) -> Vec<Opening<'a>> {
+    let mut openings = Vec::new();
+    for entry in &catalog.entries {
+        let matched = entry.meta.tsumo.iter()
+            .filter_map(|tag| Tsumo::from_str(tag))
+            .filter_map(|tsumo| tsumo.match_perm(pairs).map(|perm| (tsumo, perm)))
+            .max_by_key(|(tsumo, _)| tsumo.pairs.len());
+        if let Some((tsumo, perm)) = matched {
+            let board = if tsumo.literal { entry.board.map_puyo(|p| recolor(p, &perm)) } else { entry.board.clone() };
+            openings.push((tsumo.pairs.len(), Opening { entry, board, pattern: tsumo.pattern() }));
+        }
+    }
+    openings.sort_by_key(|(num_pairs, _)| std::cmp::Reverse(*num_pairs));
+    openings.into_iter().map(|(_, o)| o).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progression::BoardMeta;
+
+    fn pairs(s: &str) -> Vec<Pair> {
+        s.split_whitespace().map(|p| Pair::from_str(p).unwrap()).collect()
+    }
+
+    #[test]
+    fn patterns_ignore_colors_and_pair_order() {
+        assert_eq!(Tsumo::from_str("rr rg").unwrap().pattern(), "AA AB");
+        assert_eq!(Tsumo::from_str("gr yy").unwrap().pattern(), "AB CC");
+        assert_eq!(Tsumo::from_str("AAAB").unwrap().pattern(), "AA AB");
+        // the color the 2 pairs share is A
+        assert_eq!(Tsumo::from_str("gr rb").unwrap().pattern(), "AB AC");
+        assert_eq!(Tsumo::from_str("rg rb").unwrap().pattern(), "AB AC");
+
+        assert!(Tsumo::from_str("rrr").is_none());
+        assert!(Tsumo::from_str("rj").is_none());
+        assert!(Tsumo::from_str("rB").is_none());
+    }
+
+    #[test]
+    fn lookup_recolors_literal_tags() {
+        let mut catalog = Catalog::default();
+        let tagged = |tag: &str| BoardMeta { tsumo: vec![String::from(tag)], ..Default::default() };
+        catalog.add(NormalBoard::from_str("\nrrg   \nrrg   ").unwrap(), tagged("rr rr gg"));
+        catalog.add(NormalBoard::from_str("\nbb    \nbby   ").unwrap(), tagged("AA AB"));
+        catalog.add(NormalBoard::from_str("\nry    \nry    ").unwrap(), tagged("ry ry"));
+
+        let openings = lookup(&catalog, &pairs("yy yp gr"));
+        assert_eq!(openings.len(), 1);
+        assert_eq!(openings[0].pattern, "AA AB");
+        assert_eq!(openings[0].board.to_str(), "bb    \nbby   \n");
+
+        // tags in colors are recolored to the pairs
+        let openings = lookup(&catalog, &pairs("yy yy pp"));
+        assert_eq!(openings.len(), 1);
+        assert_eq!(openings[0].board.to_str(), "yyp   \nyyp   \n");
+
+        // pairs can be either way round, but keeping them the same way round
+        // decides the colors when it can
+        assert_eq!(lookup(&catalog, &pairs("bg gb")).len(), 1);
+        let openings = lookup(&catalog, &pairs("bg bg ry"));
+        assert_eq!(openings[0].board.to_str(), "bg    \nbg    \n");
+    }
+
+    #[test]
+    fn lookup_puts_longer_tags_first() {
+        let mut catalog = Catalog::default();
+        let tagged = |tag: &str| BoardMeta { tsumo: vec![String::from(tag)], ..Default::default() };
+        catalog.add(NormalBoard::from_str("\nr     \nr     ").unwrap(), tagged("AA"));
+        catalog.add(NormalBoard::from_str("\nrg    \nrr    ").unwrap(), tagged("AA AB"));
+
+        let openings = lookup(&catalog, &pairs("rr rg"));
+        let patterns: Vec<&str> = openings.iter().map(|o| o.pattern.as_str()).collect();
+        assert_eq!(patterns, vec!["AA AB", "AA"]);
+    }
+}
@@ -1,9 +1,9 @@
-use calamine::{Reader, Xlsx, open_workbook, DataType};
-use logru::ast::{self, Rule, Term};
+use calamine::{Reader, Xlsx, DataType};
 use logru::textual::TextualUniverse;
-use logru::solver::{query_dfs};
 // use std::collections::{HashMap, HashSet};
 use crate::boards::load_str_board;
+use crate::progression::{BoardMeta, Catalog};
+use crate::validate::load_realistic_board;
 
 /// parses boards in an excel sheet and loads them into 
 /// a text universe
@@ -11,12 +11,21 @@ use crate::boards::load_str_board;
 /// sheet is the name of the worksheet to parse
 /// tu is the text universe to load the parsed boards to
 /// qry is true if parsing a qry, in which case the func returns `Some(String)`
+/// strict is true to skip boards that can't happen in a real game
+/// catalog gets the labels and other metadata of each loaded board
 pub fn parse_sheet<RS>(book: &mut Xlsx<RS>, sheet: &str, tu: &mut TextualUniverse,
-    qry: bool) 
+    qry: bool, strict: bool, catalog: &mut Catalog) 
     -> Option<String>
     where RS: std::io::Read + std::io::Seek
 {
     let mut board = String::new();
+    // qry declarations like ":diff a b" go after the rows
+    let mut decls = String::new();
+    // labels since the last board, where the last one is the next board's label
+    // and any before it are headings
+    let mut labels: Vec<String> = Vec::new();
+    let mut section: Option<String> = None;
+    let mut meta = BoardMeta { sheet: sheet.to_string(), ..Default::default() };
 
     if let Some(Ok(r)) = book.worksheet_range(sheet) {
         for row in r.rows() {
@@ -25,6 +34,8 @@ pub fn parse_sheet<RS>(book: &mut Xlsx<RS>, sheet: &str, tu: &mut TextualUnivers
 //             println!("curr row ind: {}", row_counter);
 //             println!("board so far: {:?}", board);
             let mut parsed_row = String::from("\n");
+            // rows w/ labels or metadata aren't part of a board
+            let mut label_row = false;
 
             'elem:
             for elem in row {
@@ -35,20 +46,49 @@ pub fn parse_sheet<RS>(book: &mut Xlsx<RS>, sheet: &str, tu: &mut TextualUnivers
                     DataType::String(s) => {
                         if s == "end" {
                             if !qry {
-                                load_str_board(tu, &board);
+                                let loaded = if strict {
+                                    load_realistic_board(tu, &board)
+                                } else {
+                                    load_str_board(tu, &board)
+                                };
+                                meta.label = labels.pop();
+                                if !labels.is_empty() {
+                                    section = labels.pop();
+                                }
+                                meta.section = section.clone();
+                                if let Some(b) = loaded {
+                                    catalog.add(b, meta.clone());
+                                }
+                                labels.clear();
+                                meta = BoardMeta { sheet: sheet.to_string(), ..Default::default() };
                                 board = String::new();               
     
                                 // continue to the next row immediately 
                                 break 'elem;
                             } else {
+                                board.push_str(&decls);
                                 return Some(board);
                             }
 
                         } else if s.len() == 1 {
                             parsed_row.push_str(s); 
+                        } else if qry && s.starts_with(':') {
+                            decls.push('\n');
+                            decls.push_str(s);
+                        } else if qry {
+                            // qry cells like "!r" or "r|b" are a single
+                            // set-valued cell
+                            parsed_row.push_str(&format!("[{}]", s));
+                        } else if s.starts_with(':') {
+                            // metadata like ":seq gtr 2" for the next board
+                            label_row = true;
+                            if !meta.set(s) {
+                                println!("Invalid metadata: {:?}", s);
+                            }
                         } else {
-                            // useful for debugging invalid boards
-                            println!("{:?}", s);
+                            // a label for the next board, or a heading
+                            label_row = true;
+                            labels.push(s.clone());
 
                             // reset the board to avoid adding too many
                             // empty lines at the start
@@ -60,7 +100,7 @@ pub fn parse_sheet<RS>(book: &mut Xlsx<RS>, sheet: &str, tu: &mut TextualUnivers
                 }
             } // end of row loop
             // add parsed row to the curr board
-            if (parsed_row != "\n     ") && (parsed_row != "\n") {
+            if !label_row && (parsed_row != "\n     ") && (parsed_row != "\n") {
                 board.push_str(&parsed_row);
             }
         } // end of looping over the range
@@ -83,7 +123,7 @@ mod tests {
         let mut excel: Xlsx<_> = open_workbook(book).unwrap();
         let mut tu = TextualUniverse::new();
         let mut rslts = Vec::new();
-        parse_sheet(&mut excel, sheet, &mut tu, false);
+        parse_sheet(&mut excel, sheet, &mut tu, false, false, &mut Catalog::default());
 
         for rule in tu.inner().rules() { // is very messy if we don't use the prettifier
             let pretty_rule = tu.pretty().rule_to_string(rule);
@@ -95,8 +135,7 @@ mod tests {
     fn get_qry(book: &str, sheet: &str) -> String {
         let mut excel: Xlsx<_> = open_workbook(book).unwrap();
         let mut tu = TextualUniverse::new();
-        let qry = parse_sheet(&mut excel, sheet, &mut tu, true).unwrap();
-        qry
+        parse_sheet(&mut excel, sheet, &mut tu, true, false, &mut Catalog::default()).unwrap()
     }
 
     #[test]
@@ -137,6 +176,15 @@ mod tests {
         assert_eq!(rslts[0], "board(row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, r, r, b, l), row(l, l, l, l, l, l)).");
     }    
 
+    // a sheet w/ a single floating board, loaded strictly
+    #[test]
+    fn strict_skips_floating_boards() {
+        let mut excel: Xlsx<_> = open_workbook("test_excel.xlsx").unwrap();
+        let mut tu = TextualUniverse::new();
+        parse_sheet(&mut excel, "single_floating", &mut tu, false, true, &mut Catalog::default());
+        assert_eq!(tu.inner().rules().len(), 0);
+    }
+
     // a sheet w/ a single invalid board
     #[test]
     fn single_invalid() {
@@ -163,4 +211,28 @@ mod tests {
         assert_eq!(rslts[0], "board(row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, g, l, g), row(l, l, l, r, g, g), row(b, g, y, b, r, r), row(b, b, g, y, y, r), row(g, g, y, b, b, b)).");
         assert_eq!(rslts[1], "board(row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, l), row(l, l, l, l, l, y), row(l, l, l, l, g, y), row(l, l, l, l, y, g), row(y, p, b, g, p, g), row(y, y, p, b, b, p), row(p, p, b, y, p, p)).");
     }
+
+    // a sheet w/ labelled boards under 2 headings, where the 1st 2 boards
+    // are stages of a sequence
+    // labels above a board become its metadata, and the labels before them
+    // are the heading for the boards under them
+    #[test]
+    fn board_labels() {
+        let mut excel: Xlsx<_> = open_workbook("test_excel.xlsx").unwrap();
+        let mut tu = TextualUniverse::new();
+        let mut catalog = Catalog::default();
+        parse_sheet(&mut excel, "labelled", &mut tu, false, false, &mut catalog);
+
+        assert_eq!(tu.inner().rules().len(), 3);
+        let metas: Vec<(Option<&str>, Option<&str>)> = catalog.entries.iter()
+            .map(|e| (e.meta.label.as_deref(), e.meta.section.as_deref()))
+            .collect();
+        assert_eq!(metas, vec![(Some("s-1"), Some("Stairs")), (Some("s-2"), Some("Stairs")), (Some("k-1"), Some("Key"))]);
+        assert_eq!(catalog.entries[0].meta.sheet, "labelled");
+
+        let stages = catalog.sequence("stairs");
+        assert_eq!(stages.len(), 2);
+        assert_eq!(stages[1].board.to_str(), "  rr  \nrrgbby\n");
+        assert_eq!(catalog.entries[2].meta.sequence, None);
+    }
 }
@@ -0,0 +1,236 @@
+use crate::boards::{NormalBoard, Puyo};
+
+// pairs show up in this col (the 3rd one, counting fr 0)
+pub const SPAWN_COL: usize = 2;
+// a col this tall blocks pairs fr moving past it
+pub const BLOCKING_HEIGHT: usize = 12;
+// the game is over if this cell (the 12th row of the 3rd col) is filled,
+// as (row fr the bottom, col)
+pub const DEATH_CELL: (usize, usize) = (11, SPAWN_COL);
+
+// a board is close to dying if this many puyo or fewer fit in the death cell's col
+pub const DANGER_ROOM: usize = 2;
+
+// a pair of puyo, w/ the child puyo rotating around the axis puyo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pair {
+    pub axis: Puyo,
+    pub child: Puyo
+}
+
+// where the child puyo is compared to the axis puyo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Up,
+    Right,
+    Down,
+    Left
+}
+
+// how close a board is to topping out
+#[derive(Debug, PartialEq, Eq)]
+pub struct TopOut {
+    // true if the death cell is filled
+    pub dead: bool,
+    // how many more puyo can be placed in each col, where a col that can't be
+    // reached has 0, and the death cell's col stops just under the death cell
+    pub room: [usize; 6],
+    // true if the board is dead or 1 pair could kill it
+    pub danger: bool
+}
+
+// 1 way to place a pair, and the board after it falls
+#[derive(Debug)]
+pub struct Placement {
+    // the col of the axis puyo
+    pub col: usize,
+    pub rotation: Rotation,
+    pub board: NormalBoard
+}
+
+impl Pair {
+    pub fn new(axis: Puyo, child: Puyo) -> Self {
+        Pair { axis, child }
+    }
+
+    // takes in a pair like "rg" (axis first)
+    pub fn from_str(s: &str) -> Option<Self> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(a), Some(c), None) => {
+                let axis = Puyo::from_str(&a.to_string()).ok()?;
+                let child = Puyo::from_str(&c.to_string()).ok()?;
+                if axis.is_color() && child.is_color() {
+                    Some(Pair { axis, child })
+                } else {
+                    None
+                }
+            }
+            _ => None
+        }
+    }
+
+    pub fn is_double(&self) -> bool {
+        self.axis == self.child
+    }
+
+    // the puyo to drop to place the pair w/ its axis puyo in the given col,
+    // as (col, puyo), w/ the lower puyo first since it has to land first
+    // returns None if the child puyo would be off the board
+    pub fn drops(&self, col: usize, rotation: Rotation) -> Option<[(usize, Puyo); 2]> {
+        let child_col = rotation.child_col(col)?;
+        match rotation {
+            Rotation::Down => Some([(child_col, self.child), (col, self.axis)]),
+            _ => Some([(col, self.axis), (child_col, self.child)])
+        }
+    }
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [Rotation::Up, Rotation::Right, Rotation::Down, Rotation::Left];
+
+    // the col of the child puyo for an axis puyo in the given col
+    pub fn child_col(self, col: usize) -> Option<usize> {
+        match self {
+            Rotation::Up | Rotation::Down => Some(col),
+            Rotation::Right if col + 1 < 6 => Some(col + 1),
+            Rotation::Left if col > 0 => Some(col - 1),
+            _ => None
+        }
+    }
+}
+
+// the cols a pair can get to fr the spawn col, w/o going past a col that's too tall
+pub fn reachable_cols(board: &NormalBoard) -> Vec<usize> {
+    let heights = board.heights();
+    let mut cols = Vec::new();
+    if heights[SPAWN_COL] >= BLOCKING_HEIGHT {
+        return cols;
+    }
+    cols.push(SPAWN_COL);
+    // walk out fr the spawn col each way until a col blocks the way
+    let open = |col: &usize| heights[*col] < BLOCKING_HEIGHT;
+    cols.extend((0..SPAWN_COL).rev().take_while(open));
+    cols.extend(((SPAWN_COL + 1)..6).take_while(open));
+    cols.sort();
+    cols
+}
+
+// true if the game is over on the board
+pub fn is_dead(board: &NormalBoard) -> bool {
+    board.cell(DEATH_CELL.0, DEATH_CELL.1) != Puyo::Empty
+}
+
+// how close the board is to topping out
+pub fn top_out(board: &NormalBoard) -> TopOut {
+    let dead = is_dead(board);
+    let heights = board.heights();
+    let mut room = [0; 6];
+    if !dead {
+        for col in reachable_cols(board) {
+            let limit = if col == DEATH_CELL.1 { DEATH_CELL.0 } else { 13 };
+            room[col] = limit.saturating_sub(heights[col]);
+        }
+    }
+    let danger = dead || room[DEATH_CELL.1] <= DANGER_ROOM;
+    TopOut { dead, room, danger }
+}
+
+// every legal way to place a pair on a board, and the board after the pair falls
+// (before any chain goes off)
+// there are 22 for most pairs, and 11 for doubles since the other rotations give
+// the same boards
+pub fn placements(board: &NormalBoard, pair: Pair) -> Vec<Placement> {
+    let mut placements = Vec::new();
+    if is_dead(board) {
+        return placements;
+    }
+    let reachable = reachable_cols(board);
+
+    for col in 0..6 {
+        for rotation in Rotation::ALL {
+            // doubles look the same upside down or flipped sideways
+            if pair.is_double() && (rotation == Rotation::Down || rotation == Rotation::Left) {
+                continue;
+            }
+            let drops = match pair.drops(col, rotation) {
+                Some(d) => d,
+                None => continue
+            };
+            if !drops.iter().all(|(c, _)| reachable.contains(c)) {
+                continue;
+            }
+
+            let mut placed = board.clone();
+            if drops.iter().all(|(c, p)| placed.drop_puyo(*c, *p)) {
+                placements.push(Placement { col, rotation, board: placed });
+            }
+        }
+    }
+    placements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::nb;
+
+    #[test]
+    fn placement_counts() {
+        let board = nb("\nrrgbby");
+        assert_eq!(placements(&board, Pair::from_str("rg").unwrap()).len(), 22);
+        assert_eq!(placements(&board, Pair::from_str("rr").unwrap()).len(), 11);
+    }
+
+    #[test]
+    fn placed_boards() {
+        let board = nb("\nrrgbby");
+        let ps = placements(&board, Pair::from_str("rg").unwrap());
+
+        let down = ps.iter().find(|p| p.col == 0 && p.rotation == Rotation::Down).unwrap();
+        assert_eq!(down.board.to_str(), "r     \ng     \nrrgbby\n");
+
+        let left = ps.iter().find(|p| p.col == 5 && p.rotation == Rotation::Left).unwrap();
+        assert_eq!(left.board.to_str(), "    gr\nrrgbby\n");
+    }
+
+    #[test]
+    fn tall_cols_block_placements() {
+        // col 1 is 12 tall, so col 0 can't be reached
+        let mut rows = vec![" r    "; 12];
+        rows.push("rrgbby");
+        let board = nb(&rows[1..].join("\n"));
+        assert_eq!(board.heights()[1], 12);
+        assert_eq!(reachable_cols(&board), vec![2, 3, 4, 5]);
+        // 4 vertical placements each way and 3 horizontal placements each way
+        assert_eq!(placements(&board, Pair::from_str("rg").unwrap()).len(), 14);
+
+        // the death cell being filled means there's nowhere to go
+        let mut dead_rows = vec!["  r   "; 11];
+        dead_rows.push("rrgbby");
+        let dead = nb(&dead_rows.join("\n"));
+        assert!(is_dead(&dead));
+        assert!(placements(&dead, Pair::from_str("rg").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn room_per_col() {
+        let board = nb("\nrrgbby");
+        let check = top_out(&board);
+        assert_eq!(check.room, [12, 12, 10, 12, 12, 12]);
+        assert!(!check.dead && !check.danger);
+
+        // 9 tall in the death cell's col leaves room for 2
+        let mut rows = vec!["  r   "; 8];
+        rows.push(" rrgby");
+        let close = top_out(&nb(&rows.join("\n")));
+        assert_eq!(close.room[2], 2);
+        assert!(!close.dead && close.danger);
+
+        let mut dead_rows = vec!["  r   "; 11];
+        dead_rows.push("rrgbby");
+        let dead = top_out(&nb(&dead_rows.join("\n")));
+        assert!(dead.dead && dead.danger);
+        assert_eq!(dead.room, [0; 6]);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::boards::NormalBoard;
+
+// where a db board came fr and how it fits w/ other boards
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BoardMeta {
+    pub sheet: String,
+    // the heading the board is under, like "Stairs"
+    pub section: Option<String>,
+    // the board's own label, like "s-7a"
+    pub label: Option<String>,
+    // the sequence the board is a stage of, and which stage (starting at 1)
+    pub sequence: Option<(String, usize)>,
+    // the first pairs the board assumes, like "rr rg" or "AA AB"
+    pub tsumo: Vec<String>
+}
+
+// a db board and its metadata
+#[derive(Debug)]
+pub struct CatalogEntry {
+    pub board: NormalBoard,
+    pub meta: BoardMeta
+}
+
+// the metadata for every db board, in the order they were loaded
+#[derive(Debug, Default)]
+pub struct Catalog {
+    pub entries: Vec<CatalogEntry>
+}
+
+impl BoardMeta {
+    // sets the metadata for a cell like ":seq gtr 2"
+    // returns false if the cell isn't metadata
+    pub fn set(&mut self, cell: &str) -> bool {
+        let words: Vec<&str> = cell.trim_start_matches(':').split_whitespace().collect();
+        match words.as_slice() {
+            ["seq", name, stage] => match stage.parse() {
+                Ok(s) => self.sequence = Some((name.to_string(), s)),
+                Err(_) => println!("Invalid stage: {:?}", cell)
+            },
+            ["tsumo", pairs @ ..] if !pairs.is_empty() => self.tsumo.push(pairs.join(" ")),
+            _ => return false
+        }
+        true
+    }
+
+    // a short description, like "s-7a in Stairs (key), stage 2 of gtr"
+    pub fn describe(&self) -> String {
+        let mut desc = self.label.clone().unwrap_or_else(|| String::from("unlabelled"));
+        match &self.section {
+            Some(section) => desc.push_str(&format!(" in {} ({})", section, self.sheet)),
+            None => desc.push_str(&format!(" ({})", self.sheet))
+        }
+        if let Some((name, stage)) = &self.sequence {
+            desc.push_str(&format!(", stage {} of {}", stage, name));
+        }
+        desc
+    }
+}
+
+impl Catalog {
+    pub fn add(&mut self, board: NormalBoard, meta: BoardMeta) {
+        self.entries.push(CatalogEntry { board, meta });
+    }
+
+    // the metadata for a db board
+    pub fn meta_of(&self, board: &NormalBoard) -> Option<&BoardMeta> {
+        let padded = board.padded();
+        self.entries.iter().find(|e| e.board.padded() == padded).map(|e| &e.meta)
+    }
+
+    // the stages of a sequence, in order
+    pub fn sequence(&self, name: &str) -> Vec<&CatalogEntry> {
+        let mut stages: Vec<&CatalogEntry> = self.entries.iter()
+            .filter(|e| e.meta.sequence.as_ref().is_some_and(|(n, _)| n == name))
+            .collect();
+        stages.sort_by_key(|e| e.meta.sequence.as_ref().map(|(_, s)| *s));
+        stages
+    }
+
+    // all the stages of the sequence a db board is in,
+    // or nothing if it isn't in one
+    pub fn sequence_of(&self, board: &NormalBoard) -> Vec<&CatalogEntry> {
+        match self.meta_of(board).and_then(|m| m.sequence.as_ref()) {
+            Some((name, _)) => self.sequence(name),
+            None => Vec::new()
+        }
+    }
+
+    // the names of all the sequences, in the order they were first seen
+    pub fn sequence_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for entry in &self.entries {
+            if let Some((name, _)) = &entry.meta.sequence {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::nb;
+
+    fn meta(label: &str, seq: &str) -> BoardMeta {
+        let mut meta = BoardMeta { sheet: String::from("key"), label: Some(String::from(label)), ..Default::default() };
+        assert!(meta.set(seq));
+        meta
+    }
+
+    #[test]
+    fn metadata_cells() {
+        let mut m = BoardMeta::default();
+        assert!(m.set(":seq gtr 2"));
+        assert_eq!(m.sequence, Some((String::from("gtr"), 2)));
+        assert!(!m.set(":float"));
+        assert!(m.set(":tsumo AA AB"));
+        assert_eq!(m.tsumo, vec![String::from("AA AB")]);
+
+        let m = BoardMeta { sheet: String::from("key"), section: Some(String::from("Key")), ..meta("k-1", ":seq gtr 2") };
+        assert_eq!(m.describe(), "k-1 in Key (key), stage 2 of gtr");
+    }
+
+    #[test]
+    fn sequences_come_back_in_order() {
+        let mut catalog = Catalog::default();
+        catalog.add(nb("\n  gg  \nrrgbby"), meta("g-2", ":seq gtr 2"));
+        catalog.add(nb("\nyyrrbb"), meta("other", ":seq flat 1"));
+        catalog.add(nb("\nrrgbby"), meta("g-1", ":seq gtr 1"));
+        catalog.add(nb("\nrgbyrg"), BoardMeta::default());
+
+        assert_eq!(catalog.sequence_names(), vec!["gtr", "flat"]);
+
+        // any stage brings back the whole sequence
+        let stages = catalog.sequence_of(&nb("\n      \n  gg  \nrrgbby"));
+        let labels: Vec<&str> = stages.iter().map(|e| e.meta.label.as_deref().unwrap()).collect();
+        assert_eq!(labels, vec!["g-1", "g-2"]);
+
+        assert!(catalog.sequence_of(&nb("\nrgbyrg")).is_empty());
+        assert!(catalog.meta_of(&nb("\nbbbbbb")).is_none());
+    }
+}
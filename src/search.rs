@@ -0,0 +1,683 @@
+use logru::textual::TextualUniverse;
+use std::cmp::Reverse;
+use crate::boards::{InvalidQryError, NormalBoard, Puyo, TextQryBoard, db_boards, run_qry, split_qry_cells, trim_newlines};
+use crate::chain::{chain_potential, simulate};
+use crate::placement::top_out;
+use crate::similar::{recolor, superset_perm};
+
+// options for how a qry gets matched against the db
+// these are set w/ declarations in the qry, like ":shift"
+#[derive(Debug, Default)]
+pub struct QryOptions {
+    // match the pattern at every horizontal offset that fits
+    shift: bool,
+    // match the pattern at every vertical offset that fits, not just at the bottom
+    float: bool,
+    // puyo on the bottom row of the pattern need to have something under them
+    supported: bool,
+    // also match the pattern's left-right reflection
+    mirror: bool,
+    // allow up to this many mismatched cells
+    fuzzy: Option<f64>,
+    // mismatches on lower rows count for more
+    weighted: bool,
+    // checks on the whole matched board, like its col heights
+    filters: Vec<BoardFilter>,
+    // what to sort the matches by, if not by where they were found
+    sort: Option<SortKey>
+}
+
+// ways to sort matches, all fr highest to lowest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    // the longest chain a trigger can start
+    Potential,
+    // the chain the board fires by itself
+    Chain,
+    // the score of the chain the board fires by itself
+    Damage
+}
+
+// a check on a matched board that isn't about any particular cells
+#[derive(Debug)]
+pub enum BoardFilter {
+    // the col heights have to be w/in the tolerance of the given heights
+    // (None for cols that can be any height)
+    Heights([Option<usize>; 6], usize),
+    // the chain potential has to be at least this
+    ChainPotential(usize),
+    // the board can't be dead or close to dying
+    Safe
+}
+
+// a qry broken into its parts
+// the grid is the qry's rows, w/ each row split into cells
+pub struct SplitQry {
+    options: QryOptions,
+    decls: Vec<String>,
+    grid: Vec<Vec<String>>
+}
+
+// 1 way of placing the qry pattern on the board
+pub struct QryVariant {
+    grid: Vec<Vec<String>>,
+    col_offset: usize,
+    row_offset: usize,
+    mirrored: bool
+}
+
+// a db board that matched a qry, and where on the board the qry matched
+#[derive(Debug)]
+pub struct QryMatch {
+    pub board: NormalBoard,
+    // the leftmost col of the matched pattern
+    pub col_offset: usize,
+    // how many rows are below the matched pattern
+    pub row_offset: usize,
+    // true if it was the qry's reflection that matched
+    pub mirrored: bool,
+    // the num of mismatched cells (weighted by row for ":weighted"), 0 for exact matches
+    pub score: f64,
+    // the mismatched cells, as (row fr the bottom, col)
+    pub mismatches: Vec<(usize, usize)>
+}
+
+// a db board that has all the puyo of a smaller board
+#[derive(Debug)]
+pub struct SupersetMatch {
+    // the db board, recolored to use the smaller board's colors
+    pub board: NormalBoard,
+    // how many of each puyo have to be added to get to the db board
+    pub added: Vec<(Puyo, usize)>
+}
+
+impl QryOptions {
+    // sets the option for a declaration like ":shift"
+    // returns false if the declaration isn't an option,
+    // and an error if it is but its args can't be parsed
+    fn set(&mut self, decl: &str) -> Result<bool, InvalidQryError> {
+        let invalid = |what: &str| InvalidQryError::new(format!("Invalid {}: {:?}", what, decl));
+        let words: Vec<&str> = decl.trim_start_matches(':').split_whitespace().collect();
+        match words.as_slice() {
+            ["shift"] => self.shift = true,
+            ["float"] => self.float = true,
+            ["supported"] => self.supported = true,
+            ["mirror"] => self.mirror = true,
+            ["weighted"] => self.weighted = true,
+            ["safe"] => self.filters.push(BoardFilter::Safe),
+            ["fuzzy", budget] => match budget.parse::<f64>() {
+                Ok(b) => self.fuzzy = Some(b),
+                Err(_) => return Err(invalid("fuzzy budget"))
+            },
+            ["potential", min] => match min.parse() {
+                Ok(m) => self.filters.push(BoardFilter::ChainPotential(m)),
+                Err(_) => return Err(invalid("chain potential"))
+            },
+            ["sort", key] => match SortKey::from_str(key) {
+                Some(k) => self.sort = Some(k),
+                None => return Err(invalid("sort"))
+            },
+            ["heights", heights] | ["heights", heights, _] => match BoardFilter::heights(heights, words.get(2).copied()) {
+                Some(filter) => self.filters.push(filter),
+                None => return Err(invalid("heights"))
+            },
+            _ => return Ok(false)
+        }
+        Ok(true)
+    }
+}
+
+impl BoardFilter {
+    // parses heights like "3,4,*,4,2,1" and an optional tolerance like "1"
+    fn heights(heights: &str, tolerance: Option<&str>) -> Option<Self> {
+        let mut parsed = [None; 6];
+        let cols: Vec<&str> = heights.split(',').collect();
+        if cols.len() != 6 {
+            return None;
+        }
+        for (col, h) in cols.iter().enumerate() {
+            if *h != "*" {
+                parsed[col] = Some(h.parse().ok()?);
+            }
+        }
+        let tolerance = match tolerance {
+            Some(t) => t.parse().ok()?,
+            None => 0
+        };
+        Some(BoardFilter::Heights(parsed, tolerance))
+    }
+
+    pub fn matches(&self, board: &NormalBoard) -> bool {
+        match self {
+            BoardFilter::Heights(heights, tolerance) => {
+                board.heights().iter().zip(heights).all(|(actual, wanted)| match wanted {
+                    Some(h) => actual.abs_diff(*h) <= *tolerance,
+                    None => true
+                })
+            }
+            BoardFilter::ChainPotential(min) => chain_potential(board) >= *min,
+            BoardFilter::Safe => !top_out(board).danger
+        }
+    }
+}
+
+impl SortKey {
+    pub fn from_str(key: &str) -> Option<Self> {
+        match key {
+            "potential" => Some(SortKey::Potential),
+            "chain" => Some(SortKey::Chain),
+            "damage" => Some(SortKey::Damage),
+            _ => None
+        }
+    }
+
+    // the val to sort a board by
+    pub fn value(&self, board: &NormalBoard) -> usize {
+        match self {
+            SortKey::Potential => chain_potential(board),
+            SortKey::Chain => simulate(board).length(),
+            SortKey::Damage => simulate(board).score()
+        }
+    }
+}
+
+impl SplitQry {
+    // splits a qry str (like the one fr parse_sheet) into options, declarations and the grid
+    pub fn from_str(s: &str) -> Result<Self, InvalidQryError> {
+        let mut options = QryOptions::default();
+        let mut decls = Vec::new();
+        let mut grid = Vec::new();
+
+        for line in trim_newlines(s).split('\n') {
+            if line.starts_with(':') {
+                if !options.set(line)? {
+                    decls.push(line.to_string());
+                }
+            } else {
+                grid.push(split_qry_cells(line)?);
+            }
+        }
+
+        // a qry w/ only options (like ":heights") doesn't care about any cells
+        if grid.is_empty() {
+            grid.push(vec![String::from(" "); 6]);
+        }
+
+        Ok(SplitQry { options, decls, grid })
+    }
+
+    // the first and last cols that have anything other than a don't-care cell
+    fn used_cols(grid: &[Vec<String>]) -> (usize, usize) {
+        let is_used = |col: usize| grid.iter().any(|row| row.get(col).is_some_and(|cell| cell != " "));
+        match ((0..6).find(|c| is_used(*c)), (0..6).rev().find(|c| is_used(*c))) {
+            (Some(first), Some(last)) => (first, last),
+            _ => (0, 5)
+        }
+    }
+
+    // all the ways of placing the pattern on the board
+    pub fn variants(&self) -> Vec<QryVariant> {
+        let mut grids = vec![(false, self.grid.clone())];
+        if self.options.mirror {
+            // symmetric patterns would just match the same boards twice
+            // w/ shifting, only the used cols have to be symmetric, since the
+            // pattern gets moved to every col anyway
+            let mirrored = mirror_grid(&self.grid);
+            let symmetric = if self.options.shift {
+                crop_cols(&mirrored) == crop_cols(&pad_grid(&self.grid))
+            } else {
+                mirrored == pad_grid(&self.grid)
+            };
+            if !symmetric {
+                grids.push((true, mirrored));
+            }
+        }
+
+        let mut variants = Vec::new();
+        for (mirrored, grid) in grids {
+            for (col_offset, shifted) in self.col_placements(&grid) {
+                for (row_offset, placed) in self.row_placements(&shifted) {
+                    variants.push(QryVariant { grid: placed, col_offset, row_offset, mirrored });
+                }
+            }
+        }
+        variants
+    }
+
+    // the ways of placing a grid horizontally, w/ the leftmost col of each
+    fn col_placements(&self, grid: &[Vec<String>]) -> Vec<(usize, Vec<Vec<String>>)> {
+        let (first, last) = SplitQry::used_cols(grid);
+
+        if !self.options.shift {
+            return vec![(first, grid.to_vec())];
+        }
+
+        // cut out just the used cols, and then put them at each offset
+        let width = last - first + 1;
+        let mut placements = Vec::new();
+        for col_offset in 0..=(6 - width) {
+            let shifted_grid = grid.iter().map(|row| {
+                let mut shifted = vec![String::from(" "); 6];
+                for col in first..=last {
+                    if let Some(cell) = row.get(col) {
+                        shifted[col - first + col_offset] = cell.clone();
+                    }
+                }
+                shifted
+            }).collect();
+            placements.push((col_offset, shifted_grid));
+        }
+        placements
+    }
+
+    // the ways of placing a (horizontally placed) grid vertically,
+    // w/ the num of rows below the pattern for each
+    fn row_placements(&self, grid: &[Vec<String>]) -> Vec<(usize, Vec<Vec<String>>)> {
+        let is_used = |row: &Vec<String>| row.iter().any(|cell| cell != " ");
+        let first = grid.iter().position(is_used).unwrap_or(0);
+        let last = grid.iter().rposition(is_used).unwrap_or(grid.len().saturating_sub(1));
+
+        // w/o floating, the pattern stays where it was drawn,
+        // but blank rows at the bottom still count as rows below it
+        let (pattern, row_offsets) = if self.options.float {
+            let pattern = &grid[first..=last];
+            (pattern, 0..=(13usize.saturating_sub(pattern.len())))
+        } else {
+            let below = grid.len() - 1 - last;
+            (&grid[..=last], below..=below)
+        };
+
+        let mut placements = Vec::new();
+        for row_offset in row_offsets {
+            let mut placed = pattern.to_vec();
+            for i in 0..row_offset {
+                if i == 0 && self.options.supported {
+                    placed.push(support_row(&pattern[pattern.len() - 1]));
+                } else {
+                    placed.push(vec![String::from(" "); 6]);
+                }
+            }
+            placements.push((row_offset, placed));
+        }
+        placements
+    }
+}
+
+// pads each row of a grid out to the 6 cols of a board
+fn pad_grid(grid: &[Vec<String>]) -> Vec<Vec<String>> {
+    grid.iter().map(|row| {
+        let mut padded = row.clone();
+        padded.resize(6, String::from(" "));
+        padded
+    }).collect()
+}
+
+// just the used cols of a (padded) grid
+fn crop_cols(grid: &[Vec<String>]) -> Vec<Vec<String>> {
+    let (first, last) = SplitQry::used_cols(grid);
+    grid.iter().map(|row| row[first..=last].to_vec()).collect()
+}
+
+// the left-right reflection of a grid
+fn mirror_grid(grid: &[Vec<String>]) -> Vec<Vec<String>> {
+    pad_grid(grid).into_iter().map(|row| row.into_iter().rev().collect()).collect()
+}
+
+// the row under a pattern that makes sure the puyo in the pattern's bottom row
+// aren't floating
+fn support_row(bottom_row: &[String]) -> Vec<String> {
+    let mut row = vec![String::from(" "); 6];
+    for (col, cell) in bottom_row.iter().enumerate().take(6) {
+        if is_filled_cell(cell) {
+            row[col] = String::from("[!.]");
+        }
+    }
+    row
+}
+
+// qry cells that always match a puyo (or garbage)
+fn is_filled_cell(cell: &str) -> bool {
+    matches!(cell, "b" | "g" | "p" | "r" | "y" | "B" | "G" | "P" | "R" | "Y" | "J" | "*")
+}
+
+impl QryVariant {
+    // turns the variant back into a qry str that TextQryBoard::from_str can take
+    pub fn to_qry_str(&self, decls: &[String]) -> String {
+        let mut qry_str = String::new();
+        for row in &self.grid {
+            qry_str.push('\n');
+            qry_str.push_str(&row.concat());
+        }
+        for decl in decls {
+            qry_str.push('\n');
+            qry_str.push_str(decl);
+        }
+        qry_str
+    }
+}
+
+// runs a qry str against the db, trying each variant of the qry
+// returns an error if the qry can't be parsed
+pub fn run_search(tu: &mut TextualUniverse, s: &str) -> Result<Vec<QryMatch>, InvalidQryError> {
+    let split_qry = SplitQry::from_str(s)?;
+    let mut matches = match split_qry.options.fuzzy {
+        Some(budget) => run_fuzzy_search(tu, &split_qry, budget)?,
+        None => run_exact_search(tu, &split_qry)?
+    };
+    matches.retain(|m| split_qry.options.filters.iter().all(|f| f.matches(&m.board)));
+    if let Some(key) = split_qry.options.sort {
+        matches.sort_by_cached_key(|m| Reverse(key.value(&m.board)));
+    }
+    Ok(matches)
+}
+
+// checks each variant of the qry against the db w/ unification
+fn run_exact_search(tu: &mut TextualUniverse, split_qry: &SplitQry) -> Result<Vec<QryMatch>, InvalidQryError> {
+    let mut matches = Vec::new();
+    for variant in split_qry.variants() {
+        let qry_board = TextQryBoard::from_str(&variant.to_qry_str(&split_qry.decls))?;
+        for board in run_qry(tu, &qry_board)? {
+            matches.push(QryMatch {
+                board,
+                col_offset: variant.col_offset,
+                row_offset: variant.row_offset,
+                mirrored: variant.mirrored,
+                score: 0.0,
+                mismatches: Vec::new()
+            });
+        }
+    }
+    Ok(matches)
+}
+
+// checks every db board against each variant of the qry, allowing mismatches
+// each board is only listed once, for the variant w/ the fewest mismatches
+// (the 1st one on ties), and the matches are sorted fr fewest to most mismatches
+fn run_fuzzy_search(tu: &TextualUniverse, split_qry: &SplitQry, budget: f64) -> Result<Vec<QryMatch>, InvalidQryError> {
+    let boards = db_boards(tu);
+    let mut best: Vec<Option<QryMatch>> = boards.iter().map(|_| None).collect();
+
+    for variant in split_qry.variants() {
+        let qry_board = TextQryBoard::from_str(&variant.to_qry_str(&split_qry.decls))?;
+        for (board, best_match) in boards.iter().zip(best.iter_mut()) {
+            if let Some(fm) = qry_board.fuzzy_match(board, split_qry.options.weighted, budget) {
+                if best_match.as_ref().is_some_and(|m| m.score <= fm.score) {
+                    continue;
+                }
+                *best_match = Some(QryMatch {
+                    board: board.clone(),
+                    col_offset: variant.col_offset,
+                    row_offset: variant.row_offset,
+                    mirrored: variant.mirrored,
+                    score: fm.score,
+                    mismatches: fm.mismatches
+                });
+            }
+        }
+    }
+    let mut matches: Vec<QryMatch> = best.into_iter().flatten().collect();
+    matches.sort_by(|a, b| a.score.total_cmp(&b.score));
+    Ok(matches)
+}
+
+// finds the db boards that can still be built fr the board, where
+// each puyo on the board is in the same place and color (up to recoloring),
+// and the db board only adds puyo where the board is empty
+pub fn superset_search(tu: &mut TextualUniverse, board: &NormalBoard) -> Vec<SupersetMatch> {
+    // the board itself works as a qry: its puyo become color vars that have to be
+    // colored puyo, its garbage has to be garbage, and its empty cells are don't-cares
+    let mut qry_str = board.to_str().replace('j', "J");
+    let colors: Vec<String> = Puyo::COLORS.iter()
+        .filter(|c| (0..6).any(|col| board.column(col).contains(c)))
+        .map(|c| c.to_str())
+        .collect();
+    if !colors.is_empty() {
+        qry_str.push_str(&format!(":colored {}", colors.join(" ")));
+    }
+    // a board's cells and colors always make a valid qry, so these can't fail
+    let qry_board = TextQryBoard::from_str(&qry_str).unwrap();
+
+    let mut matches = Vec::new();
+    for target in run_qry(tu, &qry_board).unwrap() {
+        // match up the target's colors w/ the board's colors
+        // (the qry already checked that they do match up)
+        let perm = match superset_perm(board, &target) {
+            Some(perm) => perm,
+            None => continue
+        };
+        let recolored = target.map_puyo(|p| recolor(p, &perm));
+
+        // count what the target adds
+        let mut added: Vec<(Puyo, usize)> = Vec::new();
+        for puyo in Puyo::COLORS.iter().chain(&[Puyo::Garbage]) {
+            let count = (0..13).flat_map(|row| (0..6).map(move |col| (row, col)))
+                .filter(|(row, col)| board.cell(*row, *col) == Puyo::Empty && recolored.cell(*row, *col) == *puyo)
+                .count();
+            if count > 0 {
+                added.push((*puyo, count));
+            }
+        }
+
+        matches.push(SupersetMatch { board: recolored, added });
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::load_tu;
+
+    #[test]
+    fn shift_variants() {
+        let split_qry = SplitQry::from_str("\n  r[!r]  \n  rr  \n:shift\n:diff a b").unwrap();
+        assert_eq!(split_qry.decls, vec![String::from(":diff a b")]);
+
+        let variants = split_qry.variants();
+        assert_eq!(variants.len(), 5);
+        assert_eq!(variants[0].col_offset, 0);
+        assert_eq!(variants[0].to_qry_str(&split_qry.decls), "\nr[!r]    \nrr    \n:diff a b");
+        assert_eq!(variants[4].to_qry_str(&[]), "\n    r[!r]\n    rr");
+    }
+
+    #[test]
+    fn no_shift_keeps_the_qry() {
+        let split_qry = SplitQry::from_str("\n   rr \n      ").unwrap();
+        let variants = split_qry.variants();
+        assert_eq!(variants.len(), 1);
+        assert_eq!(variants[0].col_offset, 3);
+        assert_eq!(variants[0].row_offset, 1);
+        assert_eq!(variants[0].to_qry_str(&[]), "\n   rr \n      ");
+    }
+
+    #[test]
+    fn shifted_qry_matches_at_each_offset() {
+        let mut tu = load_tu(&["\nrrgbby", "\nbgrryy", "\nbgbgbg"]);
+
+        let matches = run_search(&mut tu, "\nrr    ").unwrap();
+        assert_eq!(matches.len(), 1);
+
+        let matches = run_search(&mut tu, "\nrr    \n:shift").unwrap();
+        let offsets: Vec<usize> = matches.iter().map(|m| m.col_offset).collect();
+        // the 1st 2 boards have 2 pairs each, so they match twice
+        assert_eq!(matches.len(), 4);
+        assert_eq!(offsets, vec![0, 2, 3, 4]);
+
+        // a bad cell rejects the whole qry instead of running w/o it
+        assert!(run_search(&mut tu, "\nrr[r|   \n:shift").is_err());
+        assert!(run_search(&mut tu, "\nrr ").is_err());
+        assert!(run_search(&mut tu, "\nrrgbbyy").is_err());
+    }
+
+    #[test]
+    fn float_variants() {
+        let split_qry = SplitQry::from_str("\n      \n  rr  \n  .r  \n      \n:float\n:supported").unwrap();
+        let variants = split_qry.variants();
+        assert_eq!(variants.len(), 12);
+        assert_eq!(variants[0].row_offset, 0);
+        assert_eq!(variants[0].to_qry_str(&[]), "\n  rr  \n  .r  ");
+        assert_eq!(variants[2].to_qry_str(&[]), "\n  rr  \n  .r  \n   [!.]  \n      ");
+    }
+
+    #[test]
+    fn floating_qry_matches_at_each_row() {
+        let mut tu = load_tu(&["\n  r   \n  bb  \nggyyrr", "\n  y   \n   b  \nggyyrr", "\n  bb  \nggyyrr"]);
+
+        // bottom-anchored only sees the bottom row
+        let matches = run_search(&mut tu, "\n  BB  ").unwrap();
+        assert_eq!(matches.len(), 0);
+
+        let matches = run_search(&mut tu, "\n  BB  \n:float").unwrap();
+        let offsets: Vec<usize> = matches.iter().map(|m| m.row_offset).collect();
+        assert_eq!(offsets, vec![1, 1]);
+
+        // the 2nd board has a gap under the top y, so it isn't supported
+        let matches = run_search(&mut tu, "\n  *.  \n:float").unwrap();
+        assert_eq!(matches.len(), 2);
+        let matches = run_search(&mut tu, "\n  *.  \n:float\n:supported").unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].row_offset, 2);
+        assert_eq!(matches[0].board.to_str(), "  r   \n  bb  \nggyyrr\n");
+    }
+
+    #[test]
+    fn mirror_variants() {
+        let split_qry = SplitQry::from_str("\n r[!r]   \nrr    \n:mirror").unwrap();
+        let variants = split_qry.variants();
+        assert_eq!(variants.len(), 2);
+        assert!(variants[1].mirrored);
+        assert_eq!(variants[1].col_offset, 3);
+        assert_eq!(variants[1].to_qry_str(&[]), "\n   [!r]r \n    rr");
+
+        // symmetric patterns only get 1 variant
+        let split_qry = SplitQry::from_str("\n  rr  \n:mirror").unwrap();
+        assert_eq!(split_qry.variants().len(), 1);
+
+        // w/ shifting, a symmetric pattern can be drawn anywhere
+        let split_qry = SplitQry::from_str("\nrr    \n:mirror\n:shift").unwrap();
+        assert!(split_qry.variants().iter().all(|v| !v.mirrored));
+    }
+
+    #[test]
+    fn mirrored_qry_matches_reflection() {
+        let mut tu = load_tu(&["\nr     \nrrb   ", "\n     r\n   brr"]);
+
+        let matches = run_search(&mut tu, "\nr     \nrrb   ").unwrap();
+        assert_eq!(matches.len(), 1);
+
+        let matches = run_search(&mut tu, "\nr     \nrrb   \n:mirror").unwrap();
+        assert_eq!(matches.len(), 2);
+        assert!(!matches[0].mirrored);
+        assert!(matches[1].mirrored);
+        assert_eq!(matches[1].board, matches[0].board.mirror());
+
+        // a symmetric pattern off to the side doesn't match each pair twice
+        let mut tu = load_tu(&["\nrrgbby", "\nbgrryy", "\nbgbgbg"]);
+        let matches = run_search(&mut tu, "\nrr    \n:shift\n:mirror").unwrap();
+        assert_eq!(matches.len(), 4);
+        assert!(matches.iter().all(|m| !m.mirrored));
+    }
+
+    #[test]
+    fn fuzzy_search_ranks_by_mismatches() {
+        let mut tu = load_tu(&["\nrrgbby", "\nrrrbby", "\nrbgbby", "\nyyyyyy"]);
+
+        let matches = run_search(&mut tu, "\nrrgbby").unwrap();
+        assert_eq!(matches.len(), 1);
+
+        let matches = run_search(&mut tu, "\nrrgbby\n:fuzzy 1").unwrap();
+        let scores: Vec<f64> = matches.iter().map(|m| m.score).collect();
+        assert_eq!(scores, vec![0.0, 1.0, 1.0]);
+        assert_eq!(matches[1].board.to_str(), "rrrbby\n");
+        assert_eq!(matches[1].mismatches, vec![(0, 2)]);
+        assert_eq!(matches[2].mismatches, vec![(0, 1)]);
+
+        // a board that's close to more than 1 variant is only listed for the best one
+        let mut tu = load_tu(&["\nrrgbby"]);
+        let matches = run_search(&mut tu, "\nRR    \n:float\n:shift\n:fuzzy 1").unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!((matches[0].score, matches[0].col_offset, matches[0].row_offset), (0.0, 0, 0));
+    }
+
+    #[test]
+    fn superset_search_finds_buildable_boards() {
+        let mut tu = load_tu(&[
+            "\n  y   \nggbyyr",
+            "\n  g   \nrrbgg ",
+            "\nrrbgg ",
+            "\n   gg \nrr gg ",
+            "\nrrbggj"
+        ]);
+        let board = NormalBoard::from_str("\nrrbgg ").unwrap();
+
+        let matches = superset_search(&mut tu, &board);
+        assert_eq!(matches.len(), 4);
+
+        // the 1st db board is recolored to the board's colors
+        assert_eq!(matches[0].board.to_str(), "  g   \nrrbggy\n");
+        assert_eq!(matches[0].added, vec![(Puyo::G, 1), (Puyo::Y, 1)]);
+        assert_eq!(matches[1].added, vec![(Puyo::G, 1)]);
+        assert!(matches[2].added.is_empty());
+        assert_eq!(matches[3].added, vec![(Puyo::Garbage, 1)]);
+    }
+
+    #[test]
+    fn superset_search_skips_garbage_under_colors() {
+        let mut tu = load_tu(&["\njjbgg ", "\nrrbggy"]);
+        let board = NormalBoard::from_str("\nrrbgg ").unwrap();
+
+        // garbage where the board has rs isn't the same color
+        let matches = superset_search(&mut tu, &board);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].board.to_str(), "rrbggy\n");
+    }
+
+    #[test]
+    fn heights_filter() {
+        let mut tu = load_tu(&["\n  r   \n  rr  \nggyyrr", "\n  yy  \nggyyrr", "\n  y   \n  y   \nggyyrr"]);
+
+        let matches = run_search(&mut tu, ":heights 1,1,3,2,1,1").unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].board.heights(), [1, 1, 3, 2, 1, 1]);
+
+        let matches = run_search(&mut tu, ":heights 1,1,3,2,1,1 1").unwrap();
+        assert_eq!(matches.len(), 3);
+
+        // combined w/ a pattern
+        let matches = run_search(&mut tu, "\n  Y   \n:float\n:heights *,*,3,*,*,*").unwrap();
+        let offsets: Vec<usize> = matches.iter().map(|m| m.row_offset).collect();
+        assert_eq!(offsets, vec![0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn potential_filter_and_sort() {
+        let mut tu = load_tu(&["\nrgbyrg", "\nrr bby", "\n   r  \n   g y\nrrrggy"]);
+
+        let matches = run_search(&mut tu, ":potential 1").unwrap();
+        assert_eq!(matches.len(), 2);
+
+        let matches = run_search(&mut tu, ":sort potential").unwrap();
+        assert_eq!(matches.len(), 3);
+        assert_eq!(matches[0].board.to_str(), "   r  \n   g y\nrrrggy\n");
+        assert_eq!(matches[2].board.to_str(), "rgbyrg\n");
+    }
+
+    #[test]
+    fn bad_option_args_reject_the_qry() {
+        let mut tu = load_tu(&["\nrrgbby"]);
+        for qry in ["\nrr    \n:fuzzy abc", ":potential x", ":sort x", ":heights 1,1,3", "\nrr    \n:shift 3"] {
+            assert!(run_search(&mut tu, qry).is_err(), "{:?}", qry);
+        }
+    }
+
+    #[test]
+    fn safe_filter() {
+        let tall = ["  r   "; 10].join("\n") + "\n rrgby";
+        let mut tu = load_tu(&["\nrrgbby", &tall]);
+        assert_eq!(run_search(&mut tu, ":heights *,*,*,*,*,*").unwrap().len(), 2);
+
+        let matches = run_search(&mut tu, ":safe").unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].board.to_str(), "rrgbby\n");
+    }
+}
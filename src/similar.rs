@@ -0,0 +1,243 @@
+use crate::boards::{NormalBoard, Puyo};
+
+// how much a cell counts in a similarity score
+// lower rows count for more, since they're harder to change later
+pub fn row_weight(row: usize) -> f64 {
+    (13 - row.min(12)) as f64 / 13.0
+}
+
+// all 120 ways of mapping the 5 colors onto themselves
+pub fn color_perms() -> Vec<[Puyo; 5]> {
+    let mut perms = Vec::with_capacity(120);
+    let mut perm = Puyo::COLORS;
+    permute(&mut perm, 0, &mut perms);
+    perms
+}
+
+fn permute(perm: &mut [Puyo; 5], k: usize, perms: &mut Vec<[Puyo; 5]>) {
+    if k == perm.len() {
+        perms.push(*perm);
+        return;
+    }
+    for i in k..perm.len() {
+        perm.swap(k, i);
+        permute(perm, k + 1, perms);
+        perm.swap(k, i);
+    }
+}
+
+// recolors a puyo w/ a color permutation, where the ith color in Puyo::COLORS
+// becomes the ith color in the perm
+pub fn recolor(puyo: Puyo, perm: &[Puyo; 5]) -> Puyo {
+    match Puyo::COLORS.iter().position(|c| *c == puyo) {
+        Some(i) => perm[i],
+        None => puyo
+    }
+}
+
+// how similar 2 boards are, fr 0 (nothing in common) to 1 (the same)
+// it's the weight of the cells that match over the weight of the cells
+// that have a puyo on either board, using the best recoloring of b
+pub fn similarity(a: &NormalBoard, b: &NormalBoard) -> f64 {
+    let mut total = 0.0;
+    let mut cells = Vec::new();
+    for row in 0..13 {
+        for col in 0..6 {
+            let (pa, pb) = (a.cell(row, col), b.cell(row, col));
+            if pa != Puyo::Empty || pb != Puyo::Empty {
+                total += row_weight(row);
+                cells.push((row, pa, pb));
+            }
+        }
+    }
+    if total == 0.0 {
+        return 1.0;
+    }
+
+    let best = color_perms().iter().map(|perm| {
+        cells.iter()
+            .filter(|(_, pa, pb)| *pa == recolor(*pb, perm))
+            .map(|(row, _, _)| row_weight(*row))
+            .sum::<f64>()
+    }).fold(0.0, f64::max);
+    best / total
+}
+
+// the fewest puyo that have to be taken off the tops of cols or dropped onto
+// the tops of cols to turn a into b, using the best recoloring of b
+// each col is a stack, so everything above the part the cols have in common
+// has to come off a and then b's puyo go back on
+pub fn edit_distance(a: &NormalBoard, b: &NormalBoard) -> usize {
+    let a_cols: Vec<Vec<Puyo>> = (0..6).map(|col| a.column(col)).collect();
+    let b_cols: Vec<Vec<Puyo>> = (0..6).map(|col| b.column(col)).collect();
+
+    color_perms().iter().map(|perm| {
+        a_cols.iter().zip(&b_cols).map(|(a_col, b_col)| {
+            let common = a_col.iter().zip(b_col)
+                .take_while(|(pa, pb)| **pa == recolor(**pb, perm))
+                .count();
+            (a_col.len() - common) + (b_col.len() - common)
+        }).sum::<usize>()
+    }).min().unwrap_or(0)
+}
+
+// if every puyo on a is in the same place on b (up to recoloring b),
+// the recoloring that turns b's colors into a's, if b has every puyo on a in
+// the same place (up to recoloring) and only adds puyo where a is empty
+// the colors are matched up cell by cell, so no color can stand for 2 others,
+// and b's colors that aren't on a get whatever colors are left
+pub fn superset_perm(a: &NormalBoard, b: &NormalBoard) -> Option<[Puyo; 5]> {
+    let mut perm: [Option<Puyo>; 5] = [None; 5];
+    for row in 0..13 {
+        for col in 0..6 {
+            let (pa, pb) = (a.cell(row, col), b.cell(row, col));
+            if pa == Puyo::Empty {
+                continue;
+            }
+            match Puyo::COLORS.iter().position(|c| *c == pb) {
+                Some(i) if pa.is_color() => match perm[i] {
+                    Some(p) if p != pa => return None,
+                    Some(_) => {}
+                    None if perm.contains(&Some(pa)) => return None,
+                    None => perm[i] = Some(pa)
+                },
+                _ if pa != pb => return None,
+                _ => {}
+            }
+        }
+    }
+    let mut unused = Puyo::COLORS.iter().filter(|c| !perm.contains(&Some(**c)));
+    Some(perm.map(|p| p.or_else(|| unused.next().copied()).unwrap_or(Puyo::Garbage)))
+}
+
+// the num of puyo b adds on top of a, if b can still be built fr a
+pub fn added_puyo(a: &NormalBoard, b: &NormalBoard) -> Option<usize> {
+    superset_perm(a, b)?;
+    Some((0..13).flat_map(|row| (0..6).map(move |col| (row, col)))
+        .filter(|(row, col)| a.cell(*row, *col) == Puyo::Empty && b.cell(*row, *col) != Puyo::Empty)
+        .count())
+}
+
+// how to rank boards when looking for the nearest ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ranking {
+    // by similarity, highest first
+    Similarity,
+    // by edit distance, lowest first
+    EditDistance
+}
+
+// a db board ranked by how similar it is to another board
+#[derive(Debug)]
+pub struct SimilarBoard {
+    pub board: NormalBoard,
+    // the similarity or the edit distance, depending on the ranking
+    pub score: f64,
+    // true if it was the board's reflection that was the most similar
+    pub mirrored: bool
+}
+
+// the n db boards most similar to a board, fr most to least similar
+// if mirror is true, a db board's reflection can be used instead
+pub fn nearest(board: &NormalBoard, db: &[NormalBoard], n: usize, mirror: bool, ranking: Ranking) -> Vec<SimilarBoard> {
+    // scores are flipped for edit distance so that higher is always better here
+    let score = |other: &NormalBoard| match ranking {
+        Ranking::Similarity => similarity(board, other),
+        Ranking::EditDistance => -(edit_distance(board, other) as f64)
+    };
+
+    let mut ranked: Vec<SimilarBoard> = db.iter().map(|db_board| {
+        let plain_score = score(db_board);
+        let mirrored_score = if mirror { score(&db_board.mirror()) } else { f64::NEG_INFINITY };
+        SimilarBoard {
+            board: db_board.clone(),
+            score: plain_score.max(mirrored_score),
+            mirrored: mirrored_score > plain_score
+        }
+    }).collect();
+
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    if ranking == Ranking::EditDistance {
+        for sb in ranked.iter_mut() {
+            sb.score = -sb.score;
+        }
+    }
+    ranked.truncate(n);
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::nb;
+
+    #[test]
+    fn similarity_ignores_color_permutations() {
+        let a = nb("\n  r   \nrrgbby");
+        assert_eq!(similarity(&a, &nb("\n  y   \nyybggr")), 1.0);
+        assert_eq!(similarity(&a, &a), 1.0);
+        assert!(similarity(&a, &nb("\n      \nrrgbby")) < 1.0);
+        assert_eq!(color_perms().len(), 120);
+    }
+
+    #[test]
+    fn lower_rows_count_for_more() {
+        let a = nb("\nrr    \nrrgbby");
+        let off_top = similarity(&a, &nb("\nrg    \nrrgbby"));
+        let off_bottom = similarity(&a, &nb("\nrr    \nrggbby"));
+        assert!(off_top > off_bottom);
+    }
+
+    #[test]
+    fn nearest_ranks_db_boards() {
+        let db = vec![nb("\nyyyyyy"), nb("\nbbggrr"), nb("\n    gg\nrrbbgg"), nb("\nggbbrr")];
+        let board = nb("\nrrbbgg");
+
+        let ranked = nearest(&board, &db, 2, false, Ranking::Similarity);
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].board, db[1]);
+        assert_eq!(ranked[0].score, 1.0);
+        assert_eq!(ranked[1].board, db[3]);
+
+        // the 3rd board is the reflection of a board w/ puyo on top of the qry
+        let ranked = nearest(&nb("\ngg    \nggbbrr"), &db, 1, true, Ranking::Similarity);
+        assert_eq!(ranked[0].board, db[2]);
+        assert!(ranked[0].mirrored);
+    }
+
+    #[test]
+    fn edit_distance_counts_col_top_changes() {
+        let a = nb("\n  r   \nrrgbby");
+        assert_eq!(edit_distance(&a, &a), 0);
+        // recoloring is free
+        assert_eq!(edit_distance(&a, &nb("\n  b   \nbbgrry")), 0);
+        // adding 1 puyo on top
+        assert_eq!(edit_distance(&a, &nb("\n  rr  \nrrgbby")), 1);
+        // changing a bottom puyo means taking off and putting back the 2 above it
+        assert_eq!(edit_distance(&nb("\n  r   \n  r   \nrrgbby"), &nb("\n  r   \n  r   \nrrrbby")), 6);
+    }
+
+    #[test]
+    fn added_puyo_needs_a_subset() {
+        let a = nb("\n  r   \nrrgbby");
+        assert_eq!(added_puyo(&a, &a), Some(0));
+        assert_eq!(added_puyo(&a, &nb("\n  bg  \nbbgrry")), Some(1));
+        let perm = superset_perm(&a, &nb("\n  bg  \nbbgrry")).unwrap();
+        assert_eq!(nb("\n  bg  \nbbgrry").map_puyo(|p| recolor(p, &perm)).to_str(), "  rg  \nrrgbby\n");
+        // a different color in the same place
+        assert_eq!(added_puyo(&a, &nb("\n  g   \nrrgbby")), None);
+        // r and g can't both become b
+        assert_eq!(added_puyo(&a, &nb("\n  b   \nbbbgyy")), None);
+        // b takes away a puyo
+        assert_eq!(added_puyo(&a, &nb("\nrrgbby")), None);
+    }
+
+    #[test]
+    fn nearest_by_edit_distance() {
+        let db = vec![nb("\nyyyyyy"), nb("\n   b  \nbbggrr"), nb("\nrrbbg ")];
+        let ranked = nearest(&nb("\nrrbbgg"), &db, 3, false, Ranking::EditDistance);
+        let scores: Vec<f64> = ranked.iter().map(|sb| sb.score).collect();
+        assert_eq!(scores, vec![1.0, 1.0, 8.0]);
+        assert_eq!(ranked[0].board, db[1]);
+    }
+}
@@ -0,0 +1,18 @@
+// helpers shared by the test modules
+
+use logru::textual::TextualUniverse;
+use crate::boards::{load_str_board, NormalBoard};
+
+/// parses a board string, panicking if it's invalid
+pub fn nb(s: &str) -> NormalBoard {
+    NormalBoard::from_str(s).unwrap()
+}
+
+/// loads each board string into a new text universe
+pub fn load_tu(boards: &[&str]) -> TextualUniverse {
+    let mut tu = TextualUniverse::new();
+    for b in boards {
+        load_str_board(&mut tu, b);
+    }
+    tu
+}
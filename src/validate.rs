@@ -0,0 +1,132 @@
+use std::fmt;
+use logru::textual::TextualUniverse;
+use crate::boards::{NormalBoard, Puyo, load_board, parse_db_board};
+use crate::chain::POP_SIZE;
+use crate::placement::{DEATH_CELL, top_out};
+
+// something about a board that looks wrong or risky
+#[derive(Debug, PartialEq, Eq)]
+pub enum Warning {
+    // the death cell is filled
+    Dead,
+    // only this many puyo fit under the death cell
+    NearDeath(usize),
+    // a puyo w/ an empty cell under it, at (row fr the bottom, col)
+    Floating(usize, usize),
+    // a puyo in a group that should have popped already, at (row fr the bottom, col),
+    // w/ the size of the group
+    Unpopped(usize, usize, usize)
+}
+
+impl Warning {
+    // true if the board can't happen in a real game
+    pub fn is_impossible(&self) -> bool {
+        matches!(self, Warning::Floating(..) | Warning::Unpopped(..))
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::Dead => write!(f, "dead: the 3rd col's 12th row is filled"),
+            Warning::NearDeath(room) => write!(f, "close to dying: only {} more puyo fit in the 3rd col", room),
+            Warning::Floating(row, col) => write!(f, "floating: the puyo at ({}, {}) has nothing under it", row, col),
+            Warning::Unpopped(row, col, size) => write!(f, "unpopped: the puyo at ({}, {}) is in a group of {} that should have popped", row, col, size)
+        }
+    }
+}
+
+// everything that looks wrong or risky about a board
+pub fn validate(board: &NormalBoard) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let check = top_out(board);
+    if check.dead {
+        warnings.push(Warning::Dead);
+    } else if check.danger {
+        warnings.push(Warning::NearDeath(check.room[DEATH_CELL.1]));
+    }
+
+    for col in 0..6 {
+        let height = board.heights()[col];
+        for row in 1..height {
+            if board.cell(row, col) != Puyo::Empty && (0..row).any(|below| board.cell(below, col) == Puyo::Empty) {
+                warnings.push(Warning::Floating(row, col));
+            }
+        }
+    }
+
+    for group in board.groups().groups_of_at_least(POP_SIZE) {
+        for (row, col) in &group.cells {
+            warnings.push(Warning::Unpopped(*row, *col, group.size()));
+        }
+    }
+    warnings
+}
+
+// true if the board could happen in a real game
+pub fn is_realistic(board: &NormalBoard) -> bool {
+    !validate(board).iter().any(|w| w.is_impossible())
+}
+
+// loads a board into a text universe like load_str_board,
+// but skips boards that can't happen in a real game
+// returns the board if it loaded
+pub fn load_realistic_board(tu: &mut TextualUniverse, s: &str) -> Option<NormalBoard> {
+    let b = parse_db_board(s)?;
+    let impossible: Vec<Warning> = validate(&b).into_iter().filter(|w| w.is_impossible()).collect();
+    if impossible.is_empty() {
+        Some(load_board(tu, b))
+    } else {
+        println!("Skipping unrealistic board:\n{}", b.to_str());
+        for warning in impossible {
+            println!("  {}", warning);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn top_out_warnings() {
+        assert!(validate(&NormalBoard::from_str("\nrrgbby").unwrap()).is_empty());
+
+        let mut rows = ["  r   ", "  g   "].repeat(5);
+        rows.truncate(9);
+        rows.push(" rbgby");
+        let close = NormalBoard::from_str(&rows.join("\n")).unwrap();
+        assert_eq!(validate(&close), vec![Warning::NearDeath(1)]);
+    }
+
+    #[test]
+    fn floating_and_unpopped() {
+        let board = NormalBoard::from_str("\n r    \n      \nrrrgby").unwrap();
+        assert_eq!(validate(&board), vec![Warning::Floating(2, 1)]);
+        assert!(!is_realistic(&board));
+
+        let board = NormalBoard::from_str("\nr     \nrrrgby").unwrap();
+        let warnings = validate(&board);
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings.contains(&Warning::Unpopped(1, 0, 4)));
+        assert!(!is_realistic(&board));
+
+        // the 13th row doesn't connect, so this doesn't pop
+        let mut rows = vec!["r     "; 4];
+        rows.extend(["g     ", "b     "].repeat(4));
+        rows.truncate(11);
+        rows.push("gbyprb");
+        rows.push("rgbypg");
+        let board = NormalBoard::from_str(&rows.join("\n")).unwrap();
+        assert!(is_realistic(&board));
+    }
+
+    #[test]
+    fn load_skips_unrealistic_boards() {
+        let mut tu = TextualUniverse::new();
+        load_realistic_board(&mut tu, "\nr     \nrrrgby");
+        load_realistic_board(&mut tu, "\nrrgbby");
+        assert_eq!(crate::boards::db_boards(&tu).len(), 1);
+    }
+}